@@ -0,0 +1,72 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// embeds the built web UI (`npm run build` in `ui`) in the binary, so it can
+// be served without any files around. without a build the list is empty and
+// the server explains how to make one
+
+// a UI build is a few megabytes at most, anything this big ended up in
+// ui/dist by mistake and would bloat the binary
+const MAX_UI_SIZE: u64 = 50 * 1024 * 1024;
+
+// symbolic links aren't followed, so they can't make it loop
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>, size: &mut u64) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.flatten() {
+        let file_type = match entry.file_type() {
+            Ok(file_type) => file_type,
+            Err(_) => continue,
+        };
+        if file_type.is_dir() {
+            collect_files(&entry.path(), files, size);
+        } else if file_type.is_file() {
+            *size += entry.metadata().map_or(0, |metadata| metadata.len());
+            files.push(entry.path());
+        }
+    }
+}
+
+fn main() {
+    let dist = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("ui")
+        .join("dist");
+    println!("cargo:rerun-if-changed={}", dist.display());
+
+    let mut files = Vec::new();
+    let mut size = 0;
+    collect_files(&dist, &mut files, &mut size);
+    if size > MAX_UI_SIZE {
+        panic!(
+            "{} holds {} bytes, more than the {} a UI build should take",
+            dist.display(),
+            size,
+            MAX_UI_SIZE
+        );
+    }
+    files.sort();
+
+    let mut code = String::from("pub static UI_ASSETS: &[(&str, &[u8])] = &[\n");
+    for file in &files {
+        let name = file
+            .strip_prefix(&dist)
+            .unwrap()
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        println!("cargo:rerun-if-changed={}", file.display());
+        code.push_str(&format!(
+            "    ({:?}, include_bytes!({:?})),\n",
+            name,
+            file.display().to_string()
+        ));
+    }
+    code.push_str("];\n");
+
+    let out = PathBuf::from(env::var("OUT_DIR").unwrap()).join("ui_assets.rs");
+    fs::write(out, code).unwrap();
+}
@@ -17,34 +17,53 @@ impl Assembler {
         Assembler { instructions }
     }
 
-    pub fn assemble_words(&self, words: Vec<&str>, memory: &mut Memory, index: usize) -> usize {
+    // nothing is written if any of the words isn't an instruction
+    pub fn assemble_words(
+        &self,
+        words: Vec<&str>,
+        memory: &mut Memory,
+        index: usize,
+    ) -> Result<usize, String> {
+        let unknown = self.unknown_words(&words);
+        if !unknown.is_empty() {
+            return Err(format!("Unknown instructions: {}", unknown.join(", ")));
+        }
         let mut i = index;
         for word in words {
-            match self.instructions.get(word) {
-                Some(instruction) => {
-                    let v = num::ToPrimitive::to_u8(instruction);
-                    if let Some(value) = v {
-                        memory.write(i, value);
-                    }
-                }
-                None => {
-                    panic!("Unknown instruction: {}", word);
-                }
-            };
+            let v = num::ToPrimitive::to_u8(&self.instructions[word]);
+            if let Some(value) = v {
+                memory.write(i, value);
+            }
             i += 1;
         }
-        i - index
+        Ok(i - index)
     }
 
-    pub fn assemble(&self, text: &str, memory: &mut Memory, index: usize) -> usize {
+    pub fn assemble(&self, text: &str, memory: &mut Memory, index: usize) -> Result<usize, String> {
         self.assemble_words(text.split_whitespace().collect(), memory, index)
     }
 
-    pub fn line_assemble(&self, text: &str, memory: &mut Memory, index: usize) -> usize {
+    pub fn line_assemble(
+        &self,
+        text: &str,
+        memory: &mut Memory,
+        index: usize,
+    ) -> Result<usize, String> {
         let words = text_to_words(text);
         self.assemble_words(words, memory, index)
     }
 
+    // the words that aren't instructions, each once
+    pub fn unknown_words<'a>(&self, words: &[&'a str]) -> Vec<&'a str> {
+        let mut unknown = Vec::new();
+        for word in words {
+            if !self.instructions.contains_key(*word) && !unknown.contains(word) {
+                unknown.push(*word);
+            }
+        }
+        unknown
+    }
+
     pub fn disassemble_to_words(&self, values: &[u8]) -> Vec<String> {
         let mut words: Vec<String> = Vec::new();
         for value in values {
@@ -72,10 +91,11 @@ impl Default for Assembler {
     }
 }
 
+// instructions are separated by whitespace, usually one per line; `#` starts
+// a comment that runs to the end of the line
 pub fn text_to_words(text: &str) -> Vec<&str> {
     text.split('\n')
-        .map(|line| line.split('#').collect::<Vec<&str>>()[0].trim())
-        .filter(|line| !line.is_empty())
+        .flat_map(|line| line.split('#').next().unwrap_or("").split_whitespace())
         .collect()
 }
 
@@ -87,41 +107,57 @@ mod tests {
     fn test_assemble() {
         let mut memory = Memory::new(10);
         let assembler = Assembler::new();
-        let amount = assembler.assemble("N1 N2", &mut memory, 0);
+        let amount = assembler.assemble("N1 N2", &mut memory, 0).unwrap();
         assert_eq!(amount, 2);
         assert_eq!(memory.values[0..amount], [1, 2]);
     }
 
+    #[test]
+    fn test_assemble_unknown() {
+        let mut memory = Memory::new(10);
+        let assembler = Assembler::new();
+        assert_eq!(
+            assembler.assemble("N1 ADDD N2 FOO ADDD", &mut memory, 0),
+            Err("Unknown instructions: ADDD, FOO".to_string())
+        );
+        assert_eq!(memory.values, Memory::new(10).values);
+    }
+
     #[test]
     fn test_line_assemble() {
         let mut memory = Memory::new(10);
         let assembler = Assembler::new();
-        let amount = assembler.line_assemble(
-            "
+        let amount = assembler
+            .line_assemble(
+                "
         N1 # 1
 
         # explanatory comment
-        N2 # 2",
-            &mut memory,
-            0,
-        );
-        assert_eq!(amount, 2);
-        assert_eq!(memory.values[0..amount], [1, 2]);
+        N2 # 2
+        N1 N2",
+                &mut memory,
+                0,
+            )
+            .unwrap();
+        assert_eq!(amount, 4);
+        assert_eq!(memory.values[0..amount], [1, 2, 1, 2]);
     }
 
     #[test]
     fn test_line_disassemble() {
         let mut memory = Memory::new(10);
         let assembler = Assembler::new();
-        let amount = assembler.line_assemble(
-            "
+        let amount = assembler
+            .line_assemble(
+                "
         N1 # 1
 
         # explanatory comment
         N2 # 2",
-            &mut memory,
-            0,
-        );
+                &mut memory,
+                0,
+            )
+            .unwrap();
         assert_eq!(
             assembler.line_disassemble(&memory.values[0..amount]),
             "N1\nN2"
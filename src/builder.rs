@@ -0,0 +1,177 @@
+use crate::island::Island;
+use crate::observer::{IslandObserver, ObserverFactory};
+use crate::overrides::{apply_overrides, Override};
+use crate::topology::Topology;
+use crate::world::{World, WorldState};
+use anyhow::Result;
+use std::fs::File;
+use std::io::BufReader;
+
+// sets up a world to run, or to step by hand:
+//
+//     let world = WorldBuilder::from_topology_file("config/simple-config.json")?
+//         .observer(|_island| Box::new(MyObserver::default()))
+//         .build()?;
+//     let mut rng = SmallRng::from_entropy();
+//     for _ in 0..1000 {
+//         world.step(&mut rng);
+//     }
+//     world.save("my-dump.aplr")?;
+
+enum Source {
+    Topology(Box<Topology>),
+    Dump(Box<WorldState>),
+}
+
+pub struct WorldBuilder {
+    source: Source,
+    overrides: Vec<Override>,
+    observers: Vec<ObserverFactory>,
+    observed_island: Option<usize>,
+}
+
+impl WorldBuilder {
+    fn new(source: Source) -> WorldBuilder {
+        WorldBuilder {
+            source,
+            overrides: Vec::new(),
+            observers: Vec::new(),
+            observed_island: None,
+        }
+    }
+
+    pub fn from_topology(topology: Topology) -> WorldBuilder {
+        WorldBuilder::new(Source::Topology(Box::new(topology)))
+    }
+
+    pub fn from_topology_file(filename: &str) -> Result<WorldBuilder> {
+        let file = BufReader::new(File::open(filename)?);
+        Ok(WorldBuilder::from_topology(Topology::from_reader(file)?))
+    }
+
+    pub fn from_world_state(world_state: WorldState) -> WorldBuilder {
+        WorldBuilder::new(Source::Dump(Box::new(world_state)))
+    }
+
+    pub fn from_dump(filename: &str) -> Result<WorldBuilder> {
+        Ok(WorldBuilder::from_world_state(WorldState::read_dump(
+            filename,
+        )?))
+    }
+
+    pub fn overrides(mut self, overrides: &[Override]) -> WorldBuilder {
+        self.overrides.extend_from_slice(overrides);
+        self
+    }
+
+    // the factory makes an observer for every island, see IslandObserver
+    pub fn observer(
+        mut self,
+        factory: impl Fn(&Island) -> Box<dyn IslandObserver> + Send + Sync + 'static,
+    ) -> WorldBuilder {
+        self.observers.push(Box::new(factory));
+        self
+    }
+
+    pub fn observed_island(mut self, island_id: usize) -> WorldBuilder {
+        self.observed_island = Some(island_id);
+        self
+    }
+
+    pub fn build(self) -> Result<World> {
+        let mut world = match self.source {
+            Source::Topology(topology) => {
+                let topology = apply_overrides(&*topology, &self.overrides)?;
+                World::try_from(&topology)?
+            }
+            Source::Dump(mut world_state) => {
+                world_state.apply_overrides(&self.overrides)?;
+                World::from_world_state(*world_state)
+            }
+        };
+        for factory in self.observers {
+            world.state().add_observer(factory);
+        }
+        if let Some(island_id) = self.observed_island {
+            world.set_observed(island_id);
+        }
+        Ok(world)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::habitat_config;
+    use crate::ticks::Ticks;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+    use std::sync::{Arc, Mutex};
+
+    struct Updates(Arc<Mutex<u64>>);
+
+    impl IslandObserver for Updates {
+        fn on_update(&mut self, _island: &Island) {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+
+    fn topology() -> Topology {
+        Topology::from_reader(
+            format!(
+                r#"{{
+                "defaults": {{
+                    "config": {config},
+                    "width": 10,
+                    "height": 10,
+                    "resources": 100,
+                    "connections": []
+                }},
+                "islands": [{{}}, {{}}],
+                "computers": []
+            }}"#,
+                config = serde_json::to_string(&habitat_config()).unwrap()
+            )
+            .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_step_and_save() {
+        let updates = Arc::new(Mutex::new(0));
+        let world = WorldBuilder::from_topology(topology())
+            .overrides(&[Override::parse("islands[1].resources=5").unwrap()])
+            .observer({
+                let updates = Arc::clone(&updates);
+                move |_island| Box::new(Updates(Arc::clone(&updates)))
+            })
+            .observed_island(1)
+            .build()
+            .unwrap();
+        world.state().configure_history(Some(Ticks(1)), Some(10));
+        let mut rng = SmallRng::from_seed([0; 32]);
+        for _ in 0..3 {
+            world.step(&mut rng);
+        }
+        world.step_island(1, &mut rng).unwrap();
+        assert!(world.step_island(2, &mut rng).is_err());
+        assert_eq!(*updates.lock().unwrap(), 7);
+        assert_eq!(world.state().get_ticks(1), Ticks(4));
+        assert_eq!(world.state().get_observed_id(), 1);
+
+        let filename =
+            std::env::temp_dir().join(format!("apilar-test-builder-{}.aplr", std::process::id()));
+        let filename = filename.to_str().unwrap();
+        world.save(filename).unwrap();
+        let loaded = WorldBuilder::from_dump(filename).unwrap().build().unwrap();
+        std::fs::remove_file(filename).unwrap();
+        assert_eq!(loaded.islands_amount(), 2);
+        assert_eq!(loaded.state().get_ticks(0), Ticks(3));
+        let island = loaded.island(1).unwrap();
+        assert_eq!(island.lock().unwrap().habitat.resources_amounts().0, 500);
+        let history = loaded.state().history(1, Some(2), None, None).unwrap();
+        assert_eq!(history.frequency, Ticks(1));
+        assert_eq!(history.samples.len(), 3);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::events::EventType;
+use crate::fill::FillScheme;
+use crate::frames::FrameFormat;
+use crate::generate::Layout;
+use crate::island::MigrationMode;
+use crate::overrides::Override;
+use crate::ticks::Ticks;
+use clap::{Args, Parser, Subcommand};
+
+// command line arguments shared by the binary and the commands that run them
+
+#[derive(Parser, Debug)]
+#[clap(author, version, about, long_about = None)]
+#[clap(propagate_version = true)]
+pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    Run(Box<RunArgs>),
+    Load(Box<RunConfigArgs>),
+    ValidateConfig(ValidateConfigArgs),
+    GenTopology(Box<GenTopologyArgs>),
+    Migrations(MigrationsArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct RunConfigArgs {
+    #[clap(value_parser)]
+    pub filename: String,
+
+    #[clap(long, default_value_t = false, value_parser)]
+    pub autosave: bool,
+
+    #[clap(long, default_value_t = 60 * 5, value_parser)]
+    pub autosave_frequency: u64,
+
+    #[clap(long, default_value_t = 1000 / 8, value_parser)]
+    pub redraw_frequency: u64,
+
+    #[clap(long, default_value_t = false, value_parser)]
+    pub no_server: bool,
+
+    /// Write every migration between islands to this file, as JSON lines
+    #[clap(long, value_parser)]
+    pub migration_log: Option<String>,
+
+    /// Write simulation events to this file, as JSON lines
+    #[clap(long, value_parser)]
+    pub event_log: Option<String>,
+
+    /// Only write these kinds of events to the event log
+    #[clap(long, value_enum, value_delimiter = ',')]
+    pub event_types: Vec<EventType>,
+
+    /// Show the world in the terminal
+    #[clap(long, default_value_t = false, value_parser)]
+    pub tui: bool,
+
+    /// How the terminal UI colors the habitat at first
+    #[clap(long, value_enum, default_value_t = FillScheme::Computers)]
+    pub tui_fill: FillScheme,
+
+    /// Write images of every island to this directory, for time-lapse movies
+    #[clap(long, value_parser)]
+    pub frames: Option<String>,
+
+    /// Ticks between frames
+    #[clap(long, default_value = "100000", value_parser = Ticks::parse)]
+    pub frame_ticks: Ticks,
+
+    /// Fill schemes to write frames with, each to its own file
+    #[clap(long, value_enum, value_delimiter = ',', default_value = "computers")]
+    pub frame_fill: Vec<FillScheme>,
+
+    #[clap(long, value_enum, default_value_t = FrameFormat::Png)]
+    pub frame_format: FrameFormat,
+
+    /// Pixels per location in frames
+    #[clap(long, default_value_t = 4, value_parser)]
+    pub frame_scale: usize,
+
+    /// Ticks between samples of island stats in the history
+    #[clap(long, value_parser = Ticks::parse)]
+    pub history_ticks: Option<Ticks>,
+
+    /// How many samples of island stats the history keeps
+    #[clap(long, value_parser)]
+    pub history_size: Option<usize>,
+
+    /// Override a parameter, like `islands[*].config.mutation_frequency=5000`
+    #[clap(long = "set", value_parser = Override::parse)]
+    pub set: Vec<Override>,
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    #[clap(flatten)]
+    pub run_config: RunConfigArgs,
+
+    #[clap(flatten)]
+    pub island: IslandArgs,
+}
+
+// used to create islands when running a .apil file directly, or when
+// generating a topology
+#[derive(Debug, Args)]
+#[clap(next_help_heading = "ISLAND OPTIONS")]
+pub struct IslandArgs {
+    #[clap(long, default_value_t = 70, value_parser)]
+    pub width: usize,
+
+    #[clap(long, default_value_t = 40, value_parser)]
+    pub height: usize,
+
+    #[clap(long, default_value_t = 400, value_parser)]
+    pub resources: u64,
+
+    #[clap(long, default_value_t = 400, value_parser)]
+    pub seed_resources: u64,
+
+    #[clap(long, default_value_t = 10, value_parser)]
+    pub instructions_per_update: usize,
+
+    #[clap(long, default_value_t = 10, value_parser)]
+    pub max_processors: usize,
+
+    #[clap(long, default_value = "10000", value_parser = Ticks::parse)]
+    pub mutation_frequency: Ticks,
+
+    #[clap(long, default_value_t = 1, value_parser)]
+    pub memory_mutation_amount: u64,
+
+    #[clap(long, default_value_t = 1, value_parser)]
+    pub memory_insert_mutation_amount: u64,
+
+    #[clap(long, default_value_t = 0, value_parser)]
+    pub memory_delete_mutation_amount: u64,
+
+    #[clap(long, default_value_t = 1, value_parser)]
+    pub processor_stack_mutation_amount: u64,
+
+    #[clap(long, default_value_t = 20000, value_parser)]
+    pub death_rate: u32,
+
+    #[clap(long, default_value_t = 8192, value_parser)]
+    pub death_memory_size: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct GenTopologyArgs {
+    #[clap(value_enum)]
+    pub layout: Layout,
+
+    #[clap(long, default_value_t = 4, value_parser)]
+    pub islands: usize,
+
+    /// Columns of the grid layout
+    #[clap(long, default_value_t = 2, value_parser)]
+    pub columns: usize,
+
+    /// Resources of the last island; resources change gradually along the layout
+    #[clap(long, value_parser)]
+    pub end_resources: Option<u64>,
+
+    /// Depth of the connected strips along the island edges
+    #[clap(long, default_value_t = 10, value_parser)]
+    pub connection_size: usize,
+
+    /// Seconds between migrations
+    #[clap(long, default_value_t = 120, value_parser)]
+    pub transmit_frequency: u64,
+
+    /// Ticks of the source island between migrations, instead of seconds
+    #[clap(long, value_parser = Ticks::parse)]
+    pub transmit_ticks: Option<Ticks>,
+
+    /// Computers that migrate each time
+    #[clap(long, default_value_t = 1, value_parser)]
+    pub migration_amount: usize,
+
+    #[clap(long, value_enum, default_value_t = MigrationMode::Move)]
+    pub migration_mode: MigrationMode,
+
+    /// Chance of extra connections in the random layout
+    #[clap(long, default_value_t = 0.3, value_parser)]
+    pub connection_probability: f64,
+
+    #[clap(long, default_value_t = 0, value_parser)]
+    pub random_seed: u64,
+
+    /// Program to put in the centre of the first island
+    #[clap(long, value_parser)]
+    pub program: Option<String>,
+
+    /// Write to this file instead of standard output
+    #[clap(long, short, value_parser)]
+    pub output: Option<String>,
+
+    #[clap(flatten)]
+    pub island: IslandArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct MigrationsArgs {
+    /// A dump file
+    #[clap(value_parser)]
+    pub filename: String,
+
+    /// Also show this many of the most recent migrations
+    #[clap(long, default_value_t = 0, value_parser)]
+    pub events: usize,
+}
+
+#[derive(Debug, Args)]
+pub struct ValidateConfigArgs {
+    #[clap(value_parser)]
+    pub filename: String,
+}
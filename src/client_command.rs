@@ -1,15 +1,103 @@
-use tokio::sync::oneshot;
+use crate::follow::{FollowMessage, SplitChoice};
+use crate::history::HistoryInfo;
+use crate::inspect::ComputerInspection;
+use crate::island::Connection;
+use crate::metrics::Metrics;
+use crate::migration::MigrationsInfo;
+use crate::topology::IslandDescription;
+use crate::view::{OverviewInfo, ViewInfo, ViewRequest};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
 
 #[derive(Debug)]
 pub enum ClientCommand {
     Stop,
     Start,
+    // stops the islands for good, after which the world saves if it
+    // autosaves and stops running
+    Quit,
     Disassemble {
         x: usize,
         y: usize,
-        respond: oneshot::Sender<Result<String, String>>,
+        respond: oneshot::Sender<Result<ComputerInspection, String>>,
     },
     Observe {
         island_id: usize,
     },
+    Follow {
+        island_id: usize,
+        x: usize,
+        y: usize,
+        split: SplitChoice,
+        respond: oneshot::Sender<Result<mpsc::Receiver<FollowMessage>, String>>,
+    },
+    // a view of the observed island, with the overview if it's already
+    // been worked out
+    View {
+        request: ViewRequest,
+        overview: Option<Arc<OverviewInfo>>,
+        respond: oneshot::Sender<ViewInfo>,
+    },
+    Metrics {
+        respond: oneshot::Sender<Metrics>,
+    },
+    History {
+        island_id: usize,
+        from: Option<u64>,
+        to: Option<u64>,
+        max_samples: Option<usize>,
+        respond: oneshot::Sender<Result<HistoryInfo, String>>,
+    },
+    Migrations {
+        limit: usize,
+        respond: oneshot::Sender<MigrationsInfo>,
+    },
+    ControlIsland {
+        island_id: usize,
+        command: IslandCommand,
+        respond: oneshot::Sender<Result<(), String>>,
+    },
+    // responds with the id of the island or connection that was added or
+    // changed
+    EditTopology {
+        edit: Box<TopologyEdit>,
+        respond: oneshot::Sender<Result<usize, String>>,
+    },
+}
+
+#[derive(Debug)]
+pub enum IslandCommand {
+    Pause,
+    Resume,
+    // pauses the island after running this many ticks
+    Step { ticks: u64 },
+    // no limit if None
+    SpeedLimit { ticks_per_second: Option<f64> },
+}
+
+#[derive(Debug)]
+pub enum TopologyEdit {
+    AddConnection {
+        island_id: usize,
+        connection: Connection,
+    },
+    UpdateConnection {
+        island_id: usize,
+        connection_id: usize,
+        connection: Connection,
+    },
+    RemoveConnection {
+        island_id: usize,
+        connection_id: usize,
+    },
+    AddIsland {
+        island: IslandDescription,
+    },
+    // the clone starts without connections
+    CloneIsland {
+        island_id: usize,
+    },
+    RemoveIsland {
+        island_id: usize,
+    },
 }
@@ -1,15 +1,34 @@
 use crate::assembler::Assembler;
-use crate::config::RunConfig;
+use crate::builder::WorldBuilder;
+use crate::cli::{
+    Cli, Commands, GenTopologyArgs, MigrationsArgs, RunArgs, RunConfigArgs, ValidateConfigArgs,
+};
+use crate::config::{quick_start_topology, RunConfig};
+use crate::generate::TopologyGenerator;
 use crate::topology::Topology;
-use crate::world::World;
 use crate::world::WorldState;
-use crate::RunConfigArgs;
-use anyhow::anyhow;
 use anyhow::Result;
+use clap::Parser;
+use std::ffi::OsStr;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Write};
+use std::path::Path;
 use std::time::Duration;
 
+// how many genotypes to show per connection
+const MIGRATION_TOP_GENOTYPES: usize = 5;
+
+// what the binary does, run inside a tokio runtime
+pub fn run_cli() -> Result<()> {
+    match &Cli::parse().command {
+        Commands::Run(cli) => run_command(cli),
+        Commands::Load(cli) => load_command(cli),
+        Commands::ValidateConfig(cli) => validate_config_command(cli),
+        Commands::GenTopology(cli) => gen_topology_command(cli),
+        Commands::Migrations(cli) => migrations_command(cli),
+    }
+}
+
 #[derive(Debug)]
 pub struct Autosave {
     pub enabled: bool,
@@ -17,32 +36,78 @@ pub struct Autosave {
 }
 
 pub fn load_command(cli: &RunConfigArgs) -> Result<()> {
-    let file = BufReader::new(File::open(cli.filename.clone())?);
-
-    let mut archive = zip::ZipArchive::new(file).unwrap();
-
-    let cbor_file = match archive.by_name("data.cbor") {
-        Ok(file) => file,
-        Err(_) => {
-            return Err(anyhow!("data.cbor not found in archive"));
-        }
-    };
-
     let run_config: RunConfig = RunConfig::from(cli);
-    let world_state: WorldState = serde_cbor::from_reader(cbor_file)?;
-    let world = World::from_world_state(world_state);
+    let world = WorldBuilder::from_dump(&cli.filename)?
+        .overrides(&cli.set)
+        .build()?;
 
     let assembler = Assembler::new();
 
     world.run(run_config, assembler)
 }
 
-pub fn run_command(cli: &RunConfigArgs) -> Result<()> {
-    let file = BufReader::new(File::open(cli.filename.clone())?);
-    let topology: Topology = serde_json::from_reader(file)?;
-    let world = World::try_from(&topology)?;
+pub fn run_command(cli: &RunArgs) -> Result<()> {
+    let island = &cli.island;
+    let cli = &cli.run_config;
+    let builder = if Path::new(&cli.filename).extension() == Some(OsStr::new("apil")) {
+        WorldBuilder::from_topology(quick_start_topology(&cli.filename, island))
+    } else {
+        WorldBuilder::from_topology_file(&cli.filename)?
+    };
+    let world = builder.overrides(&cli.set).build()?;
     let run_config = RunConfig::from(cli);
     let assembler = Assembler::new();
 
     world.run(run_config, assembler)
 }
+
+pub fn validate_config_command(cli: &ValidateConfigArgs) -> Result<()> {
+    let file = BufReader::new(File::open(cli.filename.clone())?);
+    let topology = Topology::from_reader(file)?;
+    topology.validate()?;
+    println!("{} is valid", cli.filename);
+    Ok(())
+}
+
+pub fn gen_topology_command(cli: &GenTopologyArgs) -> Result<()> {
+    let topology = TopologyGenerator::from(cli).generate()?;
+    let json = serde_json::to_string_pretty(&topology)?;
+    match &cli.output {
+        Some(filename) => {
+            let mut file = File::create(filename)?;
+            writeln!(file, "{}", json)?;
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+pub fn migrations_command(cli: &MigrationsArgs) -> Result<()> {
+    let world_state = WorldState::read_dump(&cli.filename)?;
+    let info = world_state.get_migrations().info(cli.events);
+    if info.flows.is_empty() {
+        println!("No migrations");
+    }
+    for flow in &info.flows {
+        println!(
+            "{} connection {} -> {}: {} migrants",
+            flow.from_island_id, flow.connection_id, flow.to_island_id, flow.migrants,
+        );
+        for genotype_count in flow.genotypes.iter().take(MIGRATION_TOP_GENOTYPES) {
+            println!("    {} {}", genotype_count.genotype, genotype_count.count);
+        }
+        let shown: u64 = flow
+            .genotypes
+            .iter()
+            .take(MIGRATION_TOP_GENOTYPES)
+            .map(|genotype_count| genotype_count.count)
+            .sum();
+        if shown < flow.migrants {
+            println!("    other {}", flow.migrants - shown);
+        }
+    }
+    for event in &info.events {
+        println!("{}", serde_json::to_string(event)?);
+    }
+    Ok(())
+}
@@ -5,19 +5,50 @@ use crate::want::Wants;
 use rand::rngs::SmallRng;
 use rand::seq::SliceRandom;
 use rand::Rng;
+use rustc_hash::FxHasher;
 use serde_derive::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use std::fmt;
+use std::hash::Hasher;
+use std::str::FromStr;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Computer {
+    // unique on its island, given when the computer is placed or born
+    #[serde(default)]
+    pub id: u64,
     pub resources: u64,
     pub memory: Memory,
     pub wants: Wants,
     pub processors: Vec<Processor>,
 }
 
+// a hash of the memory contents, written as hex
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, SerializeDisplay, DeserializeFromStr,
+)]
+pub struct Genotype(pub u64);
+
+impl fmt::Display for Genotype {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for Genotype {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        u64::from_str_radix(s, 16)
+            .map(Genotype)
+            .map_err(|_| format!("`{}` isn't a valid genotype", s))
+    }
+}
+
 impl Computer {
     pub fn new(size: usize, resources: u64) -> Computer {
         Computer {
+            id: 0,
             resources,
             memory: Memory::new(size),
             wants: Wants::new(),
@@ -31,11 +62,12 @@ impl Computer {
         instructions_per_update: usize,
         max_processors: usize,
         metabolism: &Metabolism,
-    ) {
+    ) -> u64 {
         self.wants.clear();
+        let mut executed = 0;
         // execute amount of instructions per processor
         for processor in &mut self.processors {
-            processor.execute_amount(
+            executed += processor.execute_amount(
                 &mut self.memory,
                 &mut self.wants,
                 rng,
@@ -90,6 +122,7 @@ impl Computer {
             }
             self.resources += amount as u64;
         }
+        executed
     }
 
     pub fn split(&mut self, address: usize) -> Option<Computer> {
@@ -98,7 +131,6 @@ impl Computer {
             return None;
         }
 
-        let parent_memory_values = self.memory.values[..address].to_vec();
         let child_memory_values = self.memory.values[address..].to_vec();
 
         let mut parent_processors: Vec<Processor> = Vec::new();
@@ -119,9 +151,11 @@ impl Computer {
 
         self.resources = parent_resources;
         self.processors = parent_processors;
-        self.memory = Memory::from_values(parent_memory_values);
+        // truncated rather than replaced, so any recorded writes are kept
+        self.memory.values.truncate(address);
 
         Some(Computer {
+            id: 0,
             resources: child_resources,
             memory: Memory::from_values(child_memory_values),
             wants: Wants::new(),
@@ -145,6 +179,12 @@ impl Computer {
         self.resources += other.resources;
     }
 
+    pub fn genotype(&self) -> Genotype {
+        let mut hasher = FxHasher::default();
+        hasher.write(&self.memory.values);
+        Genotype(hasher.finish())
+    }
+
     pub fn add_processor(&mut self, index: usize) {
         self.processors.push(Processor::new(index));
     }
@@ -261,7 +301,9 @@ mod tests {
         let words_amount = words.len();
 
         let mut computer = Computer::new(1024, 100);
-        assembler.assemble_words(words.clone(), &mut computer.memory, 0);
+        assembler
+            .assemble_words(words.clone(), &mut computer.memory, 0)
+            .unwrap();
         let mut rng = SmallRng::from_seed([0; 32]);
 
         computer.add_processor(0);
@@ -299,7 +341,9 @@ mod tests {
         let words = text_to_words(text);
 
         let mut computer = Computer::new(4, 100);
-        assembler.assemble_words(words.clone(), &mut computer.memory, 0);
+        assembler
+            .assemble_words(words.clone(), &mut computer.memory, 0)
+            .unwrap();
         computer.add_processor(0);
         computer.add_processor(2);
         computer.processors[0].set_current_head_value(0);
@@ -332,7 +376,9 @@ mod tests {
         let words = text_to_words(text);
 
         let mut computer = Computer::new(4, 100);
-        assembler.assemble_words(words.clone(), &mut computer.memory, 0);
+        assembler
+            .assemble_words(words.clone(), &mut computer.memory, 0)
+            .unwrap();
         computer.add_processor(0);
         computer.add_processor(2);
         computer.processors[0].set_current_head_value(0);
@@ -359,7 +405,9 @@ mod tests {
         let words = text_to_words(text);
 
         let mut computer = Computer::new(4, 100);
-        assembler.assemble_words(words.clone(), &mut computer.memory, 0);
+        assembler
+            .assemble_words(words.clone(), &mut computer.memory, 0)
+            .unwrap();
         computer.add_processor(0);
         computer.add_processor(2);
         computer.processors[0].set_current_head_value(0);
@@ -386,7 +434,9 @@ mod tests {
         let words = text_to_words(text);
 
         let mut computer = Computer::new(4, 100);
-        assembler.assemble_words(words.clone(), &mut computer.memory, 0);
+        assembler
+            .assemble_words(words.clone(), &mut computer.memory, 0)
+            .unwrap();
         computer.add_processor(0);
         computer.add_processor(0);
         computer.add_processor(2);
@@ -430,7 +480,9 @@ mod tests {
         let words = text_to_words(text);
 
         let mut computer = Computer::new(5, 107);
-        assembler.assemble_words(words.clone(), &mut computer.memory, 0);
+        assembler
+            .assemble_words(words.clone(), &mut computer.memory, 0)
+            .unwrap();
         computer.add_processor(0);
         computer.add_processor(2);
         computer.processors[0].set_current_head_value(0);
@@ -463,7 +515,9 @@ mod tests {
         let words = text_to_words(text);
 
         let mut computer = Computer::new(4, 100);
-        assembler.assemble_words(words.clone(), &mut computer.memory, 0);
+        assembler
+            .assemble_words(words.clone(), &mut computer.memory, 0)
+            .unwrap();
         computer.add_processor(0);
         computer.add_processor(2);
         computer.processors[0].set_current_head_value(0);
@@ -494,7 +548,9 @@ mod tests {
         let words = text_to_words(text);
 
         let mut computer = Computer::new(4, 100);
-        assembler.assemble_words(words.clone(), &mut computer.memory, 0);
+        assembler
+            .assemble_words(words.clone(), &mut computer.memory, 0)
+            .unwrap();
         computer.add_processor(0);
         computer.add_processor(1);
         computer.add_processor(2);
@@ -1,5 +1,14 @@
+use crate::cli::{GenTopologyArgs, IslandArgs, RunConfigArgs};
 use crate::command::Autosave;
-use crate::RunConfigArgs;
+use crate::events::EventType;
+use crate::fill::FillScheme;
+use crate::frames::FrameConfig;
+use crate::generate::TopologyGenerator;
+use crate::habitat::{Death, HabitatConfig, Mutation};
+use crate::instruction::Metabolism;
+use crate::ticks::Ticks;
+use crate::topology::{IslandDescription, ProgramDescription, Topology};
+use std::path::PathBuf;
 use std::time::Duration;
 
 #[derive(Debug)]
@@ -8,6 +17,17 @@ pub struct RunConfig {
     // how many milliseconds between redraws
     pub redraw_frequency: Duration,
     pub server: bool,
+    // write every migration to this file
+    pub migration_log: Option<String>,
+    // write events to this file, all of them if no types are given
+    pub event_log: Option<String>,
+    pub event_types: Vec<EventType>,
+    // show the world in the terminal, starting with this fill scheme
+    pub tui: Option<FillScheme>,
+    pub frames: Option<FrameConfig>,
+    // change how the history of the islands is sampled
+    pub history_ticks: Option<Ticks>,
+    pub history_size: Option<usize>,
 }
 
 impl From<&RunConfigArgs> for RunConfig {
@@ -20,6 +40,93 @@ impl From<&RunConfigArgs> for RunConfig {
             },
             redraw_frequency: Duration::from_millis(cli.redraw_frequency),
             server: !cli.no_server,
+            migration_log: cli.migration_log.clone(),
+            event_log: cli.event_log.clone(),
+            event_types: cli.event_types.clone(),
+            tui: cli.tui.then_some(cli.tui_fill),
+            frames: cli.frames.as_ref().map(|directory| FrameConfig {
+                directory: PathBuf::from(directory),
+                frequency: cli.frame_ticks,
+                fill_schemes: cli.frame_fill.clone(),
+                format: cli.frame_format,
+                scale: cli.frame_scale,
+            }),
+            history_ticks: cli.history_ticks,
+            history_size: cli.history_size,
+        }
+    }
+}
+
+impl From<&IslandArgs> for HabitatConfig {
+    fn from(cli: &IslandArgs) -> Self {
+        HabitatConfig {
+            instructions_per_update: cli.instructions_per_update,
+            max_processors: cli.max_processors,
+            mutation_frequency: cli.mutation_frequency,
+            mutation: Mutation {
+                overwrite_amount: cli.memory_mutation_amount,
+                insert_amount: cli.memory_insert_mutation_amount,
+                delete_amount: cli.memory_delete_mutation_amount,
+                stack_amount: cli.processor_stack_mutation_amount,
+            },
+            death: Death {
+                rate: cli.death_rate,
+                memory_size: cli.death_memory_size,
+            },
+            metabolism: Metabolism {
+                max_eat_amount: 128,
+                max_grow_amount: 16,
+                max_shrink_amount: 16,
+            },
+        }
+    }
+}
+
+// a single island world with the program in the centre
+pub fn quick_start_topology(filename: &str, cli: &IslandArgs) -> Topology {
+    Topology::single_island(
+        IslandDescription {
+            config: cli.into(),
+            width: cli.width,
+            height: cli.height,
+            resources: cli.resources,
+            disaster: None,
+            connections: Vec::new(),
+            extinction: None,
+        },
+        ProgramDescription {
+            filename: Some(filename.to_string()),
+            code: None,
+            resources: cli.seed_resources,
+            memory_size: None,
+        },
+    )
+}
+
+impl From<&GenTopologyArgs> for TopologyGenerator {
+    fn from(cli: &GenTopologyArgs) -> Self {
+        TopologyGenerator {
+            layout: cli.layout,
+            islands: cli.islands,
+            columns: cli.columns,
+            width: cli.island.width,
+            height: cli.island.height,
+            config: (&cli.island).into(),
+            start_resources: cli.island.resources,
+            end_resources: cli.end_resources.unwrap_or(cli.island.resources),
+            connection_size: cli.connection_size,
+            transmit_frequency: Duration::from_secs(cli.transmit_frequency),
+            transmit_ticks: cli.transmit_ticks,
+            migration_amount: cli.migration_amount,
+            migration_mode: cli.migration_mode,
+            connection_probability: cli.connection_probability,
+            random_seed: cli.random_seed,
+            program: cli.program.as_ref().map(|filename| ProgramDescription {
+                filename: Some(filename.clone()),
+                code: None,
+                resources: cli.island.seed_resources,
+                memory_size: None,
+            }),
         }
     }
 }
@@ -13,8 +13,10 @@ use serde_derive::{Deserialize, Serialize};
     ToPrimitive,
     Serialize,
     Deserialize,
+    Default,
 )]
 pub enum Direction {
+    #[default]
     North,
     East,
     South,
@@ -31,9 +33,3 @@ impl Direction {
         }
     }
 }
-
-impl Default for Direction {
-    fn default() -> Self {
-        Direction::North
-    }
-}
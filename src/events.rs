@@ -0,0 +1,353 @@
+use crate::computer::Genotype;
+use crate::habitat::Coords;
+use crate::island::{ExtinctionPolicy, Island};
+use crate::message::message;
+use crate::observer::{IslandObserver, ObserverFactory};
+use crate::ticks::Ticks;
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_derive::Serialize;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use tokio::sync::broadcast;
+
+// events that happen in the simulation, for anyone who wants to know
+
+// how many events a slow websocket client can lag behind before it misses some
+const EVENT_CHANNEL_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub island_id: usize,
+    pub ticks: Ticks,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EventKind {
+    // a computer split off a child
+    #[serde(rename_all = "camelCase")]
+    Birth {
+        coords: Coords,
+        computer_id: u64,
+        child_coords: Coords,
+        child_id: u64,
+    },
+    // a computer took over its neighbor
+    #[serde(rename_all = "camelCase")]
+    Merge {
+        coords: Coords,
+        computer_id: u64,
+        neighbor_coords: Coords,
+        neighbor_id: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Move {
+        from_coords: Coords,
+        to_coords: Coords,
+        computer_id: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Eat {
+        coords: Coords,
+        computer_id: u64,
+        amount: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Death {
+        coords: Coords,
+        computer_id: u64,
+        cause: DeathCause,
+    },
+    // computers killed by the disaster have their own death events
+    Wipeout {
+        coords: Coords,
+        width: usize,
+        height: usize,
+    },
+    #[serde(rename_all = "camelCase")]
+    Mutation {
+        coords: Coords,
+        computer_id: u64,
+        mutation: MutationKind,
+    },
+    // a computer placed from outside, such as a seed or a migrant
+    #[serde(rename_all = "camelCase")]
+    Arrival { coords: Coords, computer_id: u64 },
+    // the island went extinct and got new computers, which arrive with it
+    Reseed {
+        policy: ExtinctionPolicy,
+        computers: usize,
+    },
+    // the migrant gets a new id on the destination island
+    #[serde(rename_all = "camelCase")]
+    Migration {
+        from_coords: Coords,
+        computer_id: u64,
+        to_island_id: usize,
+        to_coords: Coords,
+        migrant_id: u64,
+        genotype: Genotype,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    Random,
+    MemorySize,
+    NoProcessors,
+    Killed,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MutationKind {
+    Overwrite,
+    Insert,
+    Delete,
+    Stack,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EventType {
+    Birth,
+    Merge,
+    Move,
+    Eat,
+    Death,
+    Wipeout,
+    Mutation,
+    Arrival,
+    Reseed,
+    Migration,
+}
+
+impl EventKind {
+    pub fn event_type(&self) -> EventType {
+        match self {
+            EventKind::Birth { .. } => EventType::Birth,
+            EventKind::Merge { .. } => EventType::Merge,
+            EventKind::Move { .. } => EventType::Move,
+            EventKind::Eat { .. } => EventType::Eat,
+            EventKind::Death { .. } => EventType::Death,
+            EventKind::Wipeout { .. } => EventType::Wipeout,
+            EventKind::Mutation { .. } => EventType::Mutation,
+            EventKind::Arrival { .. } => EventType::Arrival,
+            EventKind::Reseed { .. } => EventType::Reseed,
+            EventKind::Migration { .. } => EventType::Migration,
+        }
+    }
+}
+
+// writes events as JSON lines. the file is written by a thread of its own,
+// so the islands don't wait for the disk or for each other
+pub struct EventLog {
+    sender: mpsc::Sender<Event>,
+    // all events if empty
+    types: Vec<EventType>,
+}
+
+impl EventLog {
+    // makes the log of every island
+    pub fn create(filename: &str, types: Vec<EventType>) -> Result<ObserverFactory> {
+        let writer = LineWriter::new(File::create(filename)?);
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || write_events(receiver, writer));
+        Ok(Box::new(move |_island: &Island| {
+            Box::new(EventLog {
+                sender: sender.clone(),
+                types: types.clone(),
+            }) as Box<dyn IslandObserver>
+        }))
+    }
+}
+
+impl IslandObserver for EventLog {
+    fn on_event(&mut self, _island: &Island, event: &Event) {
+        if !self.types.is_empty() && !self.types.contains(&event.kind.event_type()) {
+            return;
+        }
+        // the writer is gone after a failure, which it has reported
+        let _ = self.sender.send(event.clone());
+    }
+}
+
+fn write_events(receiver: mpsc::Receiver<Event>, mut writer: impl Write) {
+    for event in receiver {
+        let written = serde_json::to_string(&event)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(writeln!(writer, "{}", json)?));
+        if let Err(error) = written {
+            message!("Could not write event log: {}", error);
+            return;
+        }
+    }
+}
+
+// hands events to websocket subscribers. island observers get them from
+// their own island instead
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+    subscribers: AtomicUsize,
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("subscribers", &self.subscribers)
+            .finish()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> EventBus {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_SIZE);
+        EventBus {
+            sender,
+            subscribers: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn is_active(&self) -> bool {
+        self.subscribers.load(Ordering::Relaxed) > 0
+    }
+
+    // call unsubscribe when done with the receiver
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.subscribers.fetch_add(1, Ordering::Relaxed);
+        self.sender.subscribe()
+    }
+
+    pub fn unsubscribe(&self) {
+        self.subscribers.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn publish(&self, events: &[Event]) {
+        if self.subscribers.load(Ordering::Relaxed) > 0 {
+            for event in events {
+                // there may be no receivers left, which is fine
+                let _ = self.sender.send(event.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: EventKind) -> Event {
+        Event {
+            island_id: 0,
+            ticks: Ticks(10),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_write_events() {
+        let (sender, receiver) = mpsc::channel();
+        let mut log = EventLog {
+            sender,
+            types: vec![EventType::Death],
+        };
+        let island = Island::new(
+            crate::habitat::Habitat::new(5, 5, 5),
+            crate::testutil::habitat_config(),
+            None,
+            Vec::new(),
+            None,
+        );
+        log.on_event(
+            &island,
+            &event(EventKind::Arrival {
+                coords: (0, 0),
+                computer_id: 1,
+            }),
+        );
+        log.on_event(
+            &island,
+            &event(EventKind::Death {
+                coords: (0, 0),
+                computer_id: 1,
+                cause: DeathCause::Random,
+            }),
+        );
+        drop(log);
+        let mut written = Vec::new();
+        write_events(receiver, &mut written);
+        let lines = String::from_utf8(written).unwrap();
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains(r#""type":"death""#));
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let bus = EventBus::default();
+        let mut rx = bus.subscribe();
+        assert!(bus.is_active());
+        bus.publish(&[event(EventKind::Eat {
+            coords: (1, 2),
+            computer_id: 3,
+            amount: 4,
+        })]);
+        let received = rx.try_recv().unwrap();
+        assert_eq!(received.kind.event_type(), EventType::Eat);
+        bus.unsubscribe();
+        assert!(!bus.is_active());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let json = serde_json::to_value(event(EventKind::Migration {
+            from_coords: (1, 2),
+            computer_id: 3,
+            to_island_id: 1,
+            to_coords: (4, 5),
+            migrant_id: 6,
+            genotype: Genotype(7),
+        }))
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "islandId": 0,
+                "ticks": 10,
+                "type": "migration",
+                "fromCoords": [1, 2],
+                "computerId": 3,
+                "toIslandId": 1,
+                "toCoords": [4, 5],
+                "migrantId": 6,
+                "genotype": "0000000000000007"
+            })
+        );
+
+        let json = serde_json::to_value(event(EventKind::Death {
+            coords: (1, 2),
+            computer_id: 3,
+            cause: DeathCause::MemorySize,
+        }))
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "islandId": 0,
+                "ticks": 10,
+                "type": "death",
+                "coords": [1, 2],
+                "computerId": 3,
+                "cause": "memory_size"
+            })
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use crate::computer::Genotype;
+use crate::info::LocationInfo;
+use clap::ValueEnum;
+
+// the ways of coloring a habitat: those of the web UI, and by genotype
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+pub const WHITE: Rgb = Rgb(255, 255, 255);
+pub const RED: Rgb = Rgb(255, 0, 0);
+
+// the 9 class sequential schemes from colorbrewer
+const BLUES: [Rgb; 9] = [
+    Rgb(247, 251, 255),
+    Rgb(222, 235, 247),
+    Rgb(198, 219, 239),
+    Rgb(158, 202, 225),
+    Rgb(107, 174, 214),
+    Rgb(66, 146, 198),
+    Rgb(33, 113, 181),
+    Rgb(8, 81, 156),
+    Rgb(8, 48, 107),
+];
+
+const GREENS: [Rgb; 9] = [
+    Rgb(247, 252, 245),
+    Rgb(229, 245, 224),
+    Rgb(199, 233, 192),
+    Rgb(161, 217, 155),
+    Rgb(116, 196, 118),
+    Rgb(65, 171, 93),
+    Rgb(35, 139, 69),
+    Rgb(0, 109, 44),
+    Rgb(0, 68, 27),
+];
+
+const REDS: [Rgb; 9] = [
+    Rgb(255, 245, 240),
+    Rgb(254, 224, 210),
+    Rgb(252, 187, 161),
+    Rgb(252, 146, 114),
+    Rgb(251, 106, 74),
+    Rgb(239, 59, 44),
+    Rgb(203, 24, 29),
+    Rgb(165, 15, 21),
+    Rgb(103, 0, 13),
+];
+
+const PURPLES: [Rgb; 9] = [
+    Rgb(252, 251, 253),
+    Rgb(239, 237, 245),
+    Rgb(218, 218, 235),
+    Rgb(188, 189, 220),
+    Rgb(158, 154, 200),
+    Rgb(128, 125, 186),
+    Rgb(106, 81, 163),
+    Rgb(84, 39, 143),
+    Rgb(63, 0, 125),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FillScheme {
+    Computers,
+    FreeResources,
+    BoundResources,
+    Memory,
+    Processors,
+    // every genotype has its own color
+    Genotype,
+}
+
+impl FillScheme {
+    pub fn next(&self) -> FillScheme {
+        match self {
+            FillScheme::Computers => FillScheme::FreeResources,
+            FillScheme::FreeResources => FillScheme::BoundResources,
+            FillScheme::BoundResources => FillScheme::Memory,
+            FillScheme::Memory => FillScheme::Processors,
+            FillScheme::Processors => FillScheme::Genotype,
+            FillScheme::Genotype => FillScheme::Computers,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FillScheme::Computers => "computers",
+            FillScheme::FreeResources => "free resources",
+            FillScheme::BoundResources => "bound resources",
+            FillScheme::Memory => "memory per computer",
+            FillScheme::Processors => "processors per computer",
+            FillScheme::Genotype => "genotype",
+        }
+    }
+
+    pub fn color(&self, location: &LocationInfo) -> Rgb {
+        if *self == FillScheme::FreeResources {
+            return scale(&BLUES, location.free_resources, 500);
+        }
+        let computer = match &location.computer {
+            Some(computer) => computer,
+            None => return WHITE,
+        };
+        match self {
+            FillScheme::Computers => RED,
+            FillScheme::BoundResources => scale(&GREENS, computer.bound_resources, 100),
+            FillScheme::Memory => scale(&REDS, computer.memory_size as u64, 1000),
+            FillScheme::Processors => scale(&PURPLES, computer.processors as u64, 10),
+            // without a genotype it's colored like any computer
+            FillScheme::Genotype => computer.genotype.map_or(RED, genotype_color),
+            FillScheme::FreeResources => unreachable!(),
+        }
+    }
+}
+
+// values of max and above get the darkest color
+fn scale(colors: &[Rgb], value: u64, max: u64) -> Rgb {
+    let value = value.min(max - 1);
+    colors[(value * colors.len() as u64 / max) as usize]
+}
+
+// a bright color picked by the hash, so that similar genotypes don't look
+// alike any more than different ones
+fn genotype_color(genotype: Genotype) -> Rgb {
+    let hue = (genotype.0 % 360) as f64;
+    hsv_to_rgb(hue, 0.75, 0.9)
+}
+
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Rgb {
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let channel = |c: f64| ((c + m) * 255.0).round() as u8;
+    Rgb(channel(r), channel(g), channel(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::info::ComputerInfo;
+
+    #[test]
+    fn test_color() {
+        let empty = LocationInfo {
+            free_resources: 250,
+            computer: None,
+        };
+        assert_eq!(FillScheme::FreeResources.color(&empty), BLUES[4]);
+        assert_eq!(FillScheme::Memory.color(&empty), WHITE);
+        let location = LocationInfo {
+            free_resources: 0,
+            computer: Some(ComputerInfo {
+                memory_size: 5000,
+                processors: 0,
+                bound_resources: 50,
+                genotype: Some(Genotype(120)),
+            }),
+        };
+        assert_eq!(FillScheme::FreeResources.color(&location), BLUES[0]);
+        assert_eq!(FillScheme::Computers.color(&location), RED);
+        assert_eq!(FillScheme::BoundResources.color(&location), GREENS[4]);
+        assert_eq!(FillScheme::Memory.color(&location), REDS[8]);
+        assert_eq!(FillScheme::Processors.color(&location), PURPLES[0]);
+        assert_eq!(FillScheme::Genotype.color(&location), Rgb(57, 230, 57));
+        assert_eq!(FillScheme::Genotype.color(&empty), WHITE);
+    }
+}
@@ -0,0 +1,591 @@
+use crate::computer::Computer;
+use crate::events::{DeathCause, Event, EventKind};
+use crate::habitat::Coords;
+use crate::inspect::{ProcessorInspection, WantsInspection};
+use crate::island::Island;
+use crate::ticks::Ticks;
+use serde_derive::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+
+// following a single computer while it runs, as it splits and moves around
+// its island, until it dies or leaves
+
+// how many updates a slow websocket client can lag behind before it misses some
+const FOLLOW_CHANNEL_SIZE: usize = 1024;
+
+// which computer to follow once the followed one splits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitChoice {
+    #[default]
+    Parent,
+    Child,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FollowMessage {
+    Update(Box<ComputerUpdate>),
+    // the last message
+    Stop { reason: StopReason },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComputerUpdate {
+    pub island_id: usize,
+    pub ticks: Ticks,
+    pub coords: Coords,
+    pub computer_id: u64,
+    pub resources: u64,
+    pub memory_size: usize,
+    pub processors: Vec<ProcessorInspection>,
+    // in the order they happened. a child doesn't have any in the update it's
+    // born
+    pub memory_writes: Vec<MemoryWrite>,
+    pub wants: WantsInspection,
+    // what happened to the computer during the update, such as moves and
+    // splits
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryWrite {
+    pub address: usize,
+    pub value: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StopReason {
+    Died {
+        cause: DeathCause,
+    },
+    #[serde(rename_all = "camelCase")]
+    MergedInto {
+        computer_id: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Migrated {
+        to_island_id: usize,
+        to_coords: Coords,
+        migrant_id: u64,
+    },
+    IslandRemoved,
+    // it's not where it should be, which shouldn't happen
+    Lost,
+}
+
+struct Follower {
+    island_id: usize,
+    computer_id: u64,
+    coords: Coords,
+    split: SplitChoice,
+    // the computer recording its memory writes during the update. it's the
+    // followed one, unless that's a child that was just born
+    recording: Option<(u64, Coords)>,
+    // where the followed computer was before the update
+    started_at: Coords,
+    // what a client too far behind hasn't received yet. the writes can't be
+    // dropped, or its copy of the memory would be wrong from then on
+    pending_writes: Vec<MemoryWrite>,
+    pending_events: Vec<Event>,
+    sender: mpsc::Sender<FollowMessage>,
+}
+
+fn computer_at(island: &mut Island, coords: Coords, computer_id: u64) -> Option<&mut Computer> {
+    island
+        .habitat
+        .get_mut(coords)
+        .computer
+        .as_mut()
+        .filter(|computer| computer.id == computer_id)
+}
+
+impl Follower {
+    fn stop(&self, reason: StopReason) {
+        // if the client is gone or too far behind it won't know why
+        let _ = self.sender.try_send(FollowMessage::Stop { reason });
+    }
+
+    fn start_recording(&mut self, island: &mut Island) {
+        self.started_at = self.coords;
+        if let Some(computer) = computer_at(island, self.coords, self.computer_id) {
+            computer.memory.record_writes();
+            self.recording = Some((self.computer_id, self.coords));
+        }
+    }
+
+    // false once there's nothing more to follow
+    fn updated(&mut self, island: &mut Island, events: &[Event]) -> bool {
+        let mut own_events = Vec::new();
+        let mut stop_reason = None;
+        for event in events {
+            let computer_id = self.computer_id;
+            match event.kind {
+                EventKind::Move {
+                    to_coords,
+                    computer_id: moved_id,
+                    ..
+                } => {
+                    if let Some((recording_id, coords)) = &mut self.recording {
+                        if *recording_id == moved_id {
+                            *coords = to_coords;
+                        }
+                    }
+                    if moved_id != computer_id {
+                        continue;
+                    }
+                    self.coords = to_coords;
+                }
+                EventKind::Birth {
+                    computer_id: parent_id,
+                    child_coords,
+                    child_id,
+                    ..
+                } => {
+                    if parent_id != computer_id {
+                        continue;
+                    }
+                    if self.split == SplitChoice::Child {
+                        self.computer_id = child_id;
+                        self.coords = child_coords;
+                        // those were the parent's
+                        self.pending_writes.clear();
+                    }
+                }
+                EventKind::Death {
+                    computer_id: dead_id,
+                    cause,
+                    ..
+                } => {
+                    if dead_id != computer_id {
+                        continue;
+                    }
+                    stop_reason = Some(StopReason::Died { cause });
+                }
+                EventKind::Merge {
+                    computer_id: merger_id,
+                    neighbor_id,
+                    ..
+                } => {
+                    if neighbor_id == computer_id {
+                        stop_reason = Some(StopReason::MergedInto {
+                            computer_id: merger_id,
+                        });
+                    } else if merger_id != computer_id {
+                        continue;
+                    }
+                }
+                EventKind::Eat {
+                    computer_id: subject_id,
+                    ..
+                }
+                | EventKind::Mutation {
+                    computer_id: subject_id,
+                    ..
+                }
+                | EventKind::Arrival {
+                    computer_id: subject_id,
+                    ..
+                } => {
+                    if subject_id != computer_id {
+                        continue;
+                    }
+                }
+                EventKind::Wipeout { .. }
+                | EventKind::Reseed { .. }
+                | EventKind::Migration { .. } => continue,
+            }
+            own_events.push(event.clone());
+            if stop_reason.is_some() {
+                break;
+            }
+        }
+
+        // recording always stops, so no computer keeps on collecting writes
+        let recorded = self.recording.take().and_then(|(recording_id, coords)| {
+            computer_at(island, coords, recording_id)
+                .map(|computer| (recording_id, computer.memory.take_writes()))
+        });
+        if let Some(reason) = stop_reason {
+            self.stop(reason);
+            return false;
+        }
+        if let Some((recording_id, writes)) = recorded {
+            if recording_id == self.computer_id {
+                self.pending_writes.extend(
+                    writes
+                        .into_iter()
+                        .map(|(address, value)| MemoryWrite { address, value }),
+                );
+            }
+        }
+        // the computer only runs now and then, and there's nothing new to
+        // tell until it does
+        let ran = island.habitat.updated() == Some(self.started_at);
+        if !ran && own_events.is_empty() {
+            return true;
+        }
+        self.pending_events.extend(own_events);
+
+        let island_id = island.id;
+        let ticks = island.ticks;
+        let computer = match computer_at(island, self.coords, self.computer_id) {
+            Some(computer) => computer,
+            None => {
+                self.stop(StopReason::Lost);
+                return false;
+            }
+        };
+        let update = ComputerUpdate {
+            island_id,
+            ticks,
+            coords: self.coords,
+            computer_id: self.computer_id,
+            resources: computer.resources,
+            memory_size: computer.memory.values.len(),
+            processors: computer.processors.iter().map(|p| p.into()).collect(),
+            memory_writes: std::mem::take(&mut self.pending_writes),
+            wants: WantsInspection::from(&*computer),
+            events: std::mem::take(&mut self.pending_events),
+        };
+        match self
+            .sender
+            .try_send(FollowMessage::Update(Box::new(update)))
+        {
+            Ok(()) => true,
+            // they go with the next update instead
+            Err(mpsc::error::TrySendError::Full(FollowMessage::Update(update))) => {
+                let update = *update;
+                self.pending_writes = update.memory_writes;
+                self.pending_events = update.events;
+                true
+            }
+            Err(mpsc::error::TrySendError::Full(_)) => true,
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+// everyone following a computer. like events, the island threads only do
+// extra work while there's someone following
+#[derive(Default)]
+pub struct Followers {
+    followers: Mutex<Vec<Follower>>,
+    has_followers: AtomicBool,
+}
+
+impl std::fmt::Debug for Followers {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Followers")
+            .field("has_followers", &self.has_followers)
+            .finish()
+    }
+}
+
+impl Followers {
+    pub fn is_active(&self) -> bool {
+        self.has_followers.load(Ordering::Relaxed)
+    }
+
+    pub fn follow(
+        &self,
+        island: &Island,
+        (x, y): Coords,
+        split: SplitChoice,
+    ) -> Result<mpsc::Receiver<FollowMessage>, String> {
+        if x >= island.habitat.width {
+            return Err("x out of range".to_string());
+        }
+        if y >= island.habitat.height {
+            return Err("y out of range".to_string());
+        }
+        let computer_id = match &island.habitat.get((x, y)).computer {
+            Some(computer) => computer.id,
+            None => return Err("no computer".to_string()),
+        };
+        let (sender, receiver) = mpsc::channel(FOLLOW_CHANNEL_SIZE);
+        self.followers.lock().unwrap().push(Follower {
+            island_id: island.id,
+            computer_id,
+            coords: (x, y),
+            split,
+            recording: None,
+            started_at: (x, y),
+            pending_writes: Vec::new(),
+            pending_events: Vec::new(),
+            sender,
+        });
+        self.has_followers.store(true, Ordering::Relaxed);
+        Ok(receiver)
+    }
+
+    pub fn before_update(&self, island: &mut Island) {
+        if !self.is_active() {
+            return;
+        }
+        for follower in self.followers.lock().unwrap().iter_mut() {
+            if follower.island_id == island.id {
+                follower.start_recording(island);
+            }
+        }
+    }
+
+    // the island has just updated, producing these events
+    pub fn updated(&self, island: &mut Island, events: &[Event]) {
+        if !self.is_active() {
+            return;
+        }
+        self.retain(|follower| follower.island_id != island.id || follower.updated(island, events));
+    }
+
+    // computers that moved to another island. copies that went there leave
+    // the original to follow
+    pub fn migrated(&self, events: &[Event]) {
+        if !self.is_active() {
+            return;
+        }
+        self.retain(|follower| {
+            for event in events {
+                if let EventKind::Migration {
+                    computer_id,
+                    to_island_id,
+                    to_coords,
+                    migrant_id,
+                    ..
+                } = event.kind
+                {
+                    if event.island_id == follower.island_id && computer_id == follower.computer_id
+                    {
+                        follower.stop(StopReason::Migrated {
+                            to_island_id,
+                            to_coords,
+                            migrant_id,
+                        });
+                        return false;
+                    }
+                }
+            }
+            true
+        });
+    }
+
+    // the islands after it are renumbered
+    pub fn island_removed(&self, island_id: usize) {
+        self.retain(|follower| {
+            if follower.island_id == island_id {
+                follower.stop(StopReason::IslandRemoved);
+                return false;
+            }
+            if follower.island_id > island_id {
+                follower.island_id -= 1;
+            }
+            true
+        });
+    }
+
+    fn retain(&self, mut f: impl FnMut(&mut Follower) -> bool) {
+        let mut followers = self.followers.lock().unwrap();
+        followers.retain_mut(|follower| f(follower));
+        self.has_followers
+            .store(!followers.is_empty(), Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::habitat::Habitat;
+    use crate::testutil::habitat_config;
+
+    fn island() -> Island {
+        Island::new(
+            Habitat::new(5, 5, 10),
+            habitat_config(),
+            None,
+            Vec::new(),
+            None,
+        )
+    }
+
+    fn event(kind: EventKind) -> Event {
+        Event {
+            island_id: 0,
+            ticks: Ticks(0),
+            kind,
+        }
+    }
+
+    fn next_update(receiver: &mut mpsc::Receiver<FollowMessage>) -> ComputerUpdate {
+        match receiver.try_recv().unwrap() {
+            FollowMessage::Update(update) => *update,
+            message => panic!("Expected an update, got {:?}", message),
+        }
+    }
+
+    #[test]
+    fn test_follow_move_and_death() {
+        let mut island = island();
+        let computer_id = island.habitat.set((0, 0), Computer::new(10, 5));
+        let followers = Followers::default();
+        assert!(followers
+            .follow(&island, (1, 0), SplitChoice::Parent)
+            .is_err());
+        let mut receiver = followers
+            .follow(&island, (0, 0), SplitChoice::Parent)
+            .unwrap();
+
+        followers.before_update(&mut island);
+        let location = island.habitat.get_mut((0, 0));
+        let mut computer = location.computer.take().unwrap();
+        computer.memory.write(3, 7);
+        island.habitat.get_mut((1, 0)).computer = Some(computer);
+        let moved = event(EventKind::Move {
+            from_coords: (0, 0),
+            to_coords: (1, 0),
+            computer_id,
+        });
+        followers.updated(&mut island, &[moved]);
+        let update = next_update(&mut receiver);
+        assert_eq!(update.coords, (1, 0));
+        assert_eq!(
+            update.memory_writes,
+            vec![MemoryWrite {
+                address: 3,
+                value: 7
+            }]
+        );
+        assert_eq!(update.events.len(), 1);
+
+        // no more writes are recorded after the update
+        let computer = island.habitat.get_mut((1, 0)).computer.as_mut().unwrap();
+        computer.memory.write(4, 7);
+        assert!(computer.memory.take_writes().is_empty());
+
+        island.habitat.die((1, 0));
+        let died = event(EventKind::Death {
+            coords: (1, 0),
+            computer_id,
+            cause: DeathCause::Killed,
+        });
+        followers.updated(&mut island, &[died]);
+        assert!(matches!(
+            receiver.try_recv().unwrap(),
+            FollowMessage::Stop {
+                reason: StopReason::Died { .. }
+            }
+        ));
+        assert!(!followers.is_active());
+    }
+
+    #[test]
+    fn test_follow_child() {
+        let mut island = island();
+        let computer_id = island.habitat.set((0, 0), Computer::new(10, 5));
+        let followers = Followers::default();
+        let mut receiver = followers
+            .follow(&island, (0, 0), SplitChoice::Child)
+            .unwrap();
+
+        followers.before_update(&mut island);
+        let parent = island.habitat.get_mut((0, 0)).computer.as_mut().unwrap();
+        parent.memory.write(8, 1);
+        let child = parent.split(5).unwrap();
+        let child_id = island.habitat.set((0, 1), child);
+        let born = event(EventKind::Birth {
+            coords: (0, 0),
+            computer_id,
+            child_coords: (0, 1),
+            child_id,
+        });
+        followers.updated(&mut island, &[born]);
+        let update = next_update(&mut receiver);
+        assert_eq!(update.computer_id, child_id);
+        assert_eq!(update.memory_size, 5);
+        // those were the parent's
+        assert!(update.memory_writes.is_empty());
+        let parent = island.habitat.get_mut((0, 0)).computer.as_mut().unwrap();
+        assert!(parent.memory.take_writes().is_empty());
+
+        followers.island_removed(0);
+        assert!(matches!(
+            receiver.try_recv().unwrap(),
+            FollowMessage::Stop {
+                reason: StopReason::IslandRemoved
+            }
+        ));
+    }
+
+    #[test]
+    fn test_follow_lagging_client() {
+        let mut island = island();
+        let computer_id = island.habitat.set((0, 0), Computer::new(10, 5));
+        let followers = Followers::default();
+        let mut receiver = followers
+            .follow(&island, (0, 0), SplitChoice::Parent)
+            .unwrap();
+
+        // nothing happened to the computer, so there's nothing to send
+        followers.before_update(&mut island);
+        followers.updated(&mut island, &[]);
+        assert!(receiver.try_recv().is_err());
+
+        let eaten = [event(EventKind::Eat {
+            coords: (0, 0),
+            computer_id,
+            amount: 1,
+        })];
+        let mut update = |address| {
+            followers.before_update(&mut island);
+            let computer = island.habitat.get_mut((0, 0)).computer.as_mut().unwrap();
+            computer.memory.write(address, 1);
+            followers.updated(&mut island, &eaten);
+        };
+        for _ in 0..FOLLOW_CHANNEL_SIZE {
+            update(0);
+        }
+        // these don't fit in the channel
+        update(1);
+        update(2);
+        for _ in 0..FOLLOW_CHANNEL_SIZE {
+            next_update(&mut receiver);
+        }
+        assert!(receiver.try_recv().is_err());
+        update(3);
+        let update = next_update(&mut receiver);
+        let addresses: Vec<usize> = update
+            .memory_writes
+            .iter()
+            .map(|write| write.address)
+            .collect();
+        assert_eq!(addresses, vec![1, 2, 3]);
+        assert_eq!(update.events.len(), 3);
+    }
+
+    #[test]
+    fn test_serialize_stop() {
+        let json = serde_json::to_value(FollowMessage::Stop {
+            reason: StopReason::Migrated {
+                to_island_id: 1,
+                to_coords: (2, 3),
+                migrant_id: 4,
+            },
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "stop",
+                "reason": {
+                    "type": "migrated",
+                    "toIslandId": 1,
+                    "toCoords": [2, 3],
+                    "migrantId": 4
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::fill::{FillScheme, Rgb, WHITE};
+use crate::image::Image;
+use crate::info::LocationInfo;
+use crate::island::Island;
+use crate::message::message;
+use crate::observer::IslandObserver;
+use crate::ticks::Ticks;
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+// writes images of every island every so many ticks, for time-lapse movies.
+// frames are numbered by the ticks of the island, so the numbering carries
+// on when a dump is loaded, and can be stitched together with something like
+//
+//     ffmpeg -framerate 25 -i island000-genotype-%06d.png movie.mp4
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FrameFormat {
+    Png,
+    Ppm,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameConfig {
+    pub directory: PathBuf,
+    pub frequency: Ticks,
+    pub fill_schemes: Vec<FillScheme>,
+    pub format: FrameFormat,
+    // pixels per location, horizontally and vertically
+    pub scale: usize,
+}
+
+// how many frames may wait for the writer before islands wait for it
+const FRAME_QUEUE: usize = 16;
+
+// the colors of an island, taken while it's locked. scaling, encoding and
+// writing are left to the writer thread
+pub struct Frame {
+    filename: PathBuf,
+    width: usize,
+    height: usize,
+    colors: Vec<Rgb>,
+}
+
+// every island gets a clone, all sending to the same writer
+#[derive(Clone)]
+pub struct FrameExporter {
+    config: FrameConfig,
+    sender: mpsc::SyncSender<Frame>,
+}
+
+impl FrameExporter {
+    pub fn new(config: FrameConfig) -> Result<FrameExporter> {
+        if config.frequency.0 == 0 {
+            return Err(anyhow!("Ticks between frames must be greater than 0"));
+        }
+        if config.scale == 0 {
+            return Err(anyhow!("Frame scale must be greater than 0"));
+        }
+        std::fs::create_dir_all(&config.directory)?;
+        let (sender, receiver) = mpsc::sync_channel(FRAME_QUEUE);
+        let (format, scale) = (config.format, config.scale);
+        std::thread::spawn(move || write_frames(receiver, format, scale));
+        Ok(FrameExporter { config, sender })
+    }
+
+    pub fn filename(&self, island_id: usize, fill_scheme: FillScheme, ticks: Ticks) -> PathBuf {
+        let extension = match self.config.format {
+            FrameFormat::Png => "png",
+            FrameFormat::Ppm => "ppm",
+        };
+        let frame = ticks.0 / self.config.frequency.0;
+        let scheme = fill_scheme
+            .to_possible_value()
+            .map_or("frame", |value| value.get_name());
+        self.config.directory.join(format!(
+            "island{:03}-{}-{:06}.{}",
+            island_id, scheme, frame, extension
+        ))
+    }
+
+    pub fn frame(&self, island: &Island, fill_scheme: FillScheme) -> Frame {
+        let habitat = &island.habitat;
+        let with_genotype = fill_scheme == FillScheme::Genotype;
+        Frame {
+            filename: self.filename(island.id, fill_scheme, island.ticks),
+            width: habitat.width,
+            height: habitat.height,
+            colors: habitat
+                .rows
+                .iter()
+                .flatten()
+                .map(|location| fill_scheme.color(&LocationInfo::new(location, with_genotype)))
+                .collect(),
+        }
+    }
+}
+
+impl Frame {
+    pub fn image(&self, scale: usize) -> Image {
+        let mut image = Image::new(self.width * scale, self.height * scale, WHITE);
+        for (i, &color) in self.colors.iter().enumerate() {
+            let (x, y) = (i % self.width, i / self.width);
+            image.fill_rect(x * scale, y * scale, scale, scale, color);
+        }
+        image
+    }
+
+    fn write(&self, format: FrameFormat, scale: usize) -> Result<()> {
+        let image = self.image(scale);
+        let mut writer = BufWriter::new(File::create(&self.filename)?);
+        match format {
+            FrameFormat::Png => image.write_png(&mut writer)?,
+            FrameFormat::Ppm => image.write_ppm(&mut writer)?,
+        }
+        Ok(writer.flush()?)
+    }
+}
+
+// runs until every exporter is gone, or writing fails
+fn write_frames(receiver: mpsc::Receiver<Frame>, format: FrameFormat, scale: usize) {
+    for frame in receiver {
+        if let Err(error) = frame.write(format, scale) {
+            message!("Could not write frame: {}", error);
+            return;
+        }
+    }
+}
+
+impl IslandObserver for FrameExporter {
+    fn ticks_frequency(&self) -> Option<Ticks> {
+        Some(self.config.frequency)
+    }
+
+    fn on_ticks(&mut self, island: &Island) {
+        for &fill_scheme in &self.config.fill_schemes {
+            // the writer is gone after a failure, which it has reported
+            if self.sender.send(self.frame(island, fill_scheme)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::Computer;
+    use crate::fill::RED;
+    use crate::habitat::Habitat;
+    use crate::testutil::habitat_config;
+
+    #[test]
+    fn test_frames() {
+        let directory =
+            std::env::temp_dir().join(format!("apilar-test-frames-{}", std::process::id()));
+        let exporter = FrameExporter::new(FrameConfig {
+            directory: directory.clone(),
+            frequency: Ticks(10),
+            fill_schemes: vec![FillScheme::Computers, FillScheme::FreeResources],
+            format: FrameFormat::Ppm,
+            scale: 2,
+        })
+        .unwrap();
+        let mut island = Island::new(
+            Habitat::new(3, 2, 5),
+            habitat_config(),
+            None,
+            Vec::new(),
+            None,
+        );
+        island.id = 1;
+        island.habitat.set((1, 0), Computer::new(1, 1));
+        island.ticks = Ticks(20);
+        for fill_scheme in [FillScheme::Computers, FillScheme::FreeResources] {
+            exporter
+                .frame(&island, fill_scheme)
+                .write(FrameFormat::Ppm, 2)
+                .unwrap();
+        }
+
+        let filename = directory.join("island001-computers-000002.ppm");
+        let ppm = std::fs::read(&filename).unwrap();
+        assert!(ppm.starts_with(b"P6\n6 4\n255\n"));
+        let pixels = &ppm[11..];
+        assert_eq!(pixels.len(), 6 * 4 * 3);
+        // the computer takes 2x2 pixels, starting at pixel 2 of the top row
+        assert_eq!(pixels[..6], [255; 6]);
+        assert_eq!(pixels[6..12], [255, 0, 0, 255, 0, 0]);
+        assert!(directory
+            .join("island001-free-resources-000002.ppm")
+            .exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        // genotypes are only worked out for their own fill
+        let frame = exporter.frame(&island, FillScheme::Genotype);
+        assert_eq!(frame.colors[0], WHITE);
+        assert_ne!(frame.colors[1], RED);
+    }
+}
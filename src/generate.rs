@@ -0,0 +1,352 @@
+use crate::habitat::HabitatConfig;
+use crate::island::{Connection, MigrationMode};
+use crate::rectangle::Rectangle;
+use crate::ticks::Ticks;
+use crate::topology::{ComputerDescription, IslandDescription, ProgramDescription, Topology};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rand::SeedableRng;
+use std::time::Duration;
+
+// generates topologies for common island layouts
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Layout {
+    // islands in a row, each connected to the next
+    Line,
+    // a line where the last island connects to the first
+    Ring,
+    // a 2d grid of islands connected to their neighbors
+    Grid,
+    // a hub island connected to all others
+    Star,
+    // a random connected graph
+    Random,
+}
+
+#[derive(Debug)]
+pub struct TopologyGenerator {
+    pub layout: Layout,
+    pub islands: usize,
+    // only used for the grid layout
+    pub columns: usize,
+    pub width: usize,
+    pub height: usize,
+    pub config: HabitatConfig,
+    // resources go from start to end along the layout
+    pub start_resources: u64,
+    pub end_resources: u64,
+    // the depth of the strips along the island edges that are connected
+    pub connection_size: usize,
+    pub transmit_frequency: Duration,
+    // when set, migrate on ticks instead of on the wall clock
+    pub transmit_ticks: Option<Ticks>,
+    pub migration_amount: usize,
+    pub migration_mode: MigrationMode,
+    // chance of an extra connection between two islands in a random layout
+    pub connection_probability: f64,
+    pub random_seed: u64,
+    // placed in the centre of the first island
+    pub program: Option<ProgramDescription>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Side {
+    North,
+    East,
+    South,
+    West,
+    Centre,
+}
+
+impl Side {
+    fn opposite(&self) -> Side {
+        match self {
+            Side::North => Side::South,
+            Side::East => Side::West,
+            Side::South => Side::North,
+            Side::West => Side::East,
+            Side::Centre => Side::Centre,
+        }
+    }
+}
+
+impl TopologyGenerator {
+    pub fn generate(&self) -> Result<Topology> {
+        if self.islands == 0 {
+            return Err(anyhow!("At least one island is required"));
+        }
+        if self.layout == Layout::Grid && self.columns == 0 {
+            return Err(anyhow!("At least one column is required"));
+        }
+        if self.connection_size == 0
+            || self.connection_size > self.width
+            || self.connection_size > self.height
+        {
+            return Err(anyhow!(
+                "Connection size must be between 1 and the island width and height"
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.connection_probability) {
+            return Err(anyhow!("Connection probability must be between 0 and 1"));
+        }
+        let positions = self.positions();
+        let islands = positions
+            .iter()
+            .map(|position| IslandDescription {
+                config: self.config.clone(),
+                width: self.width,
+                height: self.height,
+                resources: self.resources_at(*position),
+                disaster: None,
+                connections: Vec::new(),
+                extinction: None,
+            })
+            .collect();
+        let mut topology = Topology {
+            islands,
+            computers: Vec::new(),
+            seeds: Vec::new(),
+        };
+        for (from_id, to_id, side) in self.edges() {
+            self.connect(&mut topology, from_id, to_id, side);
+            self.connect(&mut topology, to_id, from_id, side.opposite());
+        }
+        if let Some(program) = &self.program {
+            topology.computers.push(ComputerDescription {
+                island_id: 0,
+                x: self.width / 2,
+                y: self.height / 2,
+                program: program.clone(),
+            });
+        }
+        topology.validate()?;
+        Ok(topology)
+    }
+
+    fn rows(&self) -> usize {
+        self.islands.div_ceil(self.columns)
+    }
+
+    // where each island is along the layout, from 0 to 1
+    fn positions(&self) -> Vec<f64> {
+        let along = |i: usize, amount: usize| {
+            if amount > 1 {
+                i as f64 / (amount - 1) as f64
+            } else {
+                0.0
+            }
+        };
+        (0..self.islands)
+            .map(|i| match self.layout {
+                Layout::Line | Layout::Ring | Layout::Random => along(i, self.islands),
+                Layout::Grid => along(i % self.columns, self.columns),
+                Layout::Star => {
+                    if i == 0 {
+                        0.0
+                    } else {
+                        1.0
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn resources_at(&self, position: f64) -> u64 {
+        let start = self.start_resources as f64;
+        let end = self.end_resources as f64;
+        (start + (end - start) * position).round() as u64
+    }
+
+    // connected pairs of islands, and the side of the first island the
+    // connection is on
+    fn edges(&self) -> Vec<(usize, usize, Side)> {
+        let amount = self.islands;
+        match self.layout {
+            Layout::Line => (1..amount).map(|i| (i - 1, i, Side::East)).collect(),
+            Layout::Ring => {
+                let mut edges: Vec<(usize, usize, Side)> =
+                    (1..amount).map(|i| (i - 1, i, Side::East)).collect();
+                if amount > 2 {
+                    edges.push((amount - 1, 0, Side::East));
+                }
+                edges
+            }
+            Layout::Grid => {
+                let mut edges = Vec::new();
+                for i in 0..amount {
+                    let (column, row) = (i % self.columns, i / self.columns);
+                    if column + 1 < self.columns && i + 1 < amount {
+                        edges.push((i, i + 1, Side::East));
+                    }
+                    if row + 1 < self.rows() && i + self.columns < amount {
+                        edges.push((i, i + self.columns, Side::South));
+                    }
+                }
+                edges
+            }
+            Layout::Star => (1..amount).map(|i| (0, i, Side::Centre)).collect(),
+            Layout::Random => {
+                let mut rng = SmallRng::seed_from_u64(self.random_seed);
+                let mut order: Vec<usize> = (0..amount).collect();
+                order.shuffle(&mut rng);
+                // a random spanning tree makes sure everything is connected
+                let mut edges: Vec<(usize, usize, Side)> = (1..amount)
+                    .map(|i| {
+                        let j = rng.gen_range(0..i);
+                        (order[j], order[i], Side::Centre)
+                    })
+                    .collect();
+                for from_id in 0..amount {
+                    for to_id in from_id + 1..amount {
+                        let connected = edges.iter().any(|(a, b, _)| {
+                            (*a == from_id && *b == to_id) || (*a == to_id && *b == from_id)
+                        });
+                        if !connected && rng.gen_bool(self.connection_probability) {
+                            edges.push((from_id, to_id, Side::Centre));
+                        }
+                    }
+                }
+                edges
+            }
+        }
+    }
+
+    fn connect(&self, topology: &mut Topology, from_id: usize, to_id: usize, side: Side) {
+        topology.islands[from_id].connections.push(Connection {
+            from_rect: self.rect(side),
+            to_rect: self.rect(side.opposite()),
+            to_id,
+            transmit_frequency: match self.transmit_ticks {
+                Some(_) => None,
+                None => Some(self.transmit_frequency),
+            },
+            transmit_ticks: self.transmit_ticks,
+            amount: self.migration_amount,
+            mode: self.migration_mode,
+            filter: None,
+            mutation: None,
+        });
+    }
+
+    fn rect(&self, side: Side) -> Rectangle {
+        let size = self.connection_size;
+        match side {
+            Side::North => Rectangle::new(0, 0, self.width, size),
+            Side::East => Rectangle::new(self.width - size, 0, size, self.height),
+            Side::South => Rectangle::new(0, self.height - size, self.width, size),
+            Side::West => Rectangle::new(0, 0, size, self.height),
+            Side::Centre => Rectangle::new(
+                (self.width - size) / 2,
+                (self.height - size) / 2,
+                size,
+                size,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::habitat_config;
+
+    fn generator(layout: Layout, islands: usize) -> TopologyGenerator {
+        TopologyGenerator {
+            layout,
+            islands,
+            columns: 3,
+            width: 20,
+            height: 10,
+            config: habitat_config(),
+            start_resources: 100,
+            end_resources: 400,
+            connection_size: 4,
+            transmit_frequency: Duration::from_secs(10),
+            transmit_ticks: None,
+            migration_amount: 1,
+            migration_mode: MigrationMode::Move,
+            connection_probability: 0.5,
+            random_seed: 0,
+            program: None,
+        }
+    }
+
+    fn to_ids(topology: &Topology, island_id: usize) -> Vec<usize> {
+        topology.islands[island_id]
+            .connections
+            .iter()
+            .map(|connection| connection.to_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_line() {
+        let topology = generator(Layout::Line, 4).generate().unwrap();
+        assert!(topology.validate().is_ok());
+        assert_eq!(to_ids(&topology, 0), vec![1]);
+        assert_eq!(to_ids(&topology, 1), vec![0, 2]);
+        assert_eq!(to_ids(&topology, 3), vec![2]);
+        let resources: Vec<u64> = topology
+            .islands
+            .iter()
+            .map(|island| island.resources)
+            .collect();
+        assert_eq!(resources, vec![100, 200, 300, 400]);
+    }
+
+    #[test]
+    fn test_ring() {
+        let topology = generator(Layout::Ring, 4).generate().unwrap();
+        assert!(topology.validate().is_ok());
+        assert_eq!(to_ids(&topology, 0), vec![1, 3]);
+        assert_eq!(to_ids(&topology, 3), vec![2, 0]);
+    }
+
+    #[test]
+    fn test_grid() {
+        // 3 columns, 2 rows with the last spot empty
+        let topology = generator(Layout::Grid, 5).generate().unwrap();
+        assert!(topology.validate().is_ok());
+        assert_eq!(to_ids(&topology, 0), vec![1, 3]);
+        assert_eq!(to_ids(&topology, 2), vec![1]);
+        assert_eq!(to_ids(&topology, 4), vec![1, 3]);
+        assert_eq!(topology.islands[2].resources, 400);
+        assert_eq!(topology.islands[3].resources, 100);
+    }
+
+    #[test]
+    fn test_star() {
+        let topology = generator(Layout::Star, 4).generate().unwrap();
+        assert!(topology.validate().is_ok());
+        assert_eq!(to_ids(&topology, 0), vec![1, 2, 3]);
+        assert_eq!(to_ids(&topology, 2), vec![0]);
+    }
+
+    #[test]
+    fn test_connection_too_big() {
+        let mut generator = generator(Layout::Line, 4);
+        generator.connection_size = 11;
+        assert!(generator.generate().is_err());
+    }
+
+    #[test]
+    fn test_random_is_connected() {
+        let topology = generator(Layout::Random, 8).generate().unwrap();
+        assert!(topology.validate().is_ok());
+        let mut reached = vec![0];
+        let mut i = 0;
+        while i < reached.len() {
+            for to_id in to_ids(&topology, reached[i]) {
+                if !reached.contains(&to_id) {
+                    reached.push(to_id);
+                }
+            }
+            i += 1;
+        }
+        assert_eq!(reached.len(), 8);
+    }
+}
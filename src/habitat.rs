@@ -1,10 +1,13 @@
 use crate::assembler::Assembler;
 use crate::direction::Direction;
+use crate::events::{DeathCause, EventKind, MutationKind};
+use crate::inspect::ComputerInspection;
 use crate::instruction::Metabolism;
 use crate::rectangle::Rectangle;
 use crate::{computer::Computer, ticks::Ticks};
 use rand::rngs::SmallRng;
 use rand::Rng;
+use rustc_hash::FxHashMap;
 use serde_derive::{Deserialize, Serialize};
 
 const CONNECTION_SAMPLING_TRIES: u64 = 2u64.pow(5);
@@ -20,9 +23,18 @@ pub struct Habitat {
     pub width: usize,
     pub height: usize,
     pub rows: Vec<Vec<Location>>,
+    #[serde(default)]
+    next_id: u64,
+    // events are only recorded when this is set
+    #[serde(skip)]
+    events: Option<Vec<EventKind>>,
+    // the location the last update ran, so followers know whether their
+    // computer did
+    #[serde(skip)]
+    updated: Option<Coords>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Mutation {
     pub overwrite_amount: u64,
     pub insert_amount: u64,
@@ -30,6 +42,24 @@ pub struct Mutation {
     pub stack_amount: u64,
 }
 
+impl Mutation {
+    // mutate a single computer, such as a migrant in transit
+    pub fn apply(&self, computer: &mut Computer, rng: &mut SmallRng) {
+        for _ in 0..self.overwrite_amount {
+            computer.mutate_memory_overwrite(rng);
+        }
+        for _ in 0..self.insert_amount {
+            computer.mutate_memory_insert(rng);
+        }
+        for _ in 0..self.delete_amount {
+            computer.mutate_memory_delete(rng);
+        }
+        for _ in 0..self.stack_amount {
+            computer.mutate_processors(rng);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Death {
     pub rate: u32,
@@ -63,9 +93,42 @@ impl Habitat {
             width,
             height,
             rows,
+            next_id: 0,
+            events: None,
+            updated: None,
+        }
+    }
+
+    pub fn updated(&self) -> Option<Coords> {
+        self.updated
+    }
+
+    pub fn record_events(&mut self, record: bool) {
+        if !record {
+            self.events = None;
+        } else if self.events.is_none() {
+            self.events = Some(Vec::new());
+        }
+    }
+
+    pub fn take_events(&mut self) -> Vec<EventKind> {
+        match &mut self.events {
+            Some(events) => std::mem::take(events),
+            None => Vec::new(),
         }
     }
 
+    fn record(&mut self, event: EventKind) {
+        if let Some(events) = &mut self.events {
+            events.push(event);
+        }
+    }
+
+    fn new_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
     fn neighbor_coords(&self, coords: Coords, direction: Direction) -> Coords {
         let (x, y) = coords;
         let ix = x as i32;
@@ -81,8 +144,16 @@ impl Habitat {
         (rx as usize, ry as usize)
     }
 
-    pub fn set(&mut self, (x, y): Coords, computer: Computer) {
-        self.rows[y][x].computer = Some(computer);
+    // place a computer from outside the habitat, returns its new id
+    pub fn set(&mut self, coords: Coords, mut computer: Computer) -> u64 {
+        let computer_id = self.new_id();
+        computer.id = computer_id;
+        self.get_mut(coords).computer = Some(computer);
+        self.record(EventKind::Arrival {
+            coords,
+            computer_id,
+        });
+        computer_id
     }
 
     pub fn get(&self, (x, y): Coords) -> &Location {
@@ -103,14 +174,27 @@ impl Habitat {
         self.get(coords).computer.is_none()
     }
 
-    pub fn update(&mut self, rng: &mut SmallRng, config: &HabitatConfig) {
+    // returns how many instructions were executed
+    pub fn update(&mut self, rng: &mut SmallRng, config: &HabitatConfig) -> u64 {
         let coords = self.get_random_coords(rng);
+        self.updated = Some(coords);
 
         let location = self.get_mut(coords);
-        location.update(rng, config);
+        let computer_id = location.computer.as_ref().map(|computer| computer.id);
+        let instructions = location.update(rng, config);
+        if let Some(computer_id) = computer_id {
+            if self.is_empty(coords) {
+                self.record(EventKind::Death {
+                    coords,
+                    computer_id,
+                    cause: DeathCause::NoProcessors,
+                });
+                return instructions;
+            }
+        }
 
         if self.death(rng, coords, &config.death) {
-            return;
+            return instructions;
         }
 
         if let Some((neighbor_coords, address)) = self.want_split(coords, rng) {
@@ -134,6 +218,7 @@ impl Habitat {
         if let Some(amount) = self.want_eat(coords, rng) {
             self.eat(coords, amount);
         }
+        instructions
     }
 
     pub fn mutate(&mut self, rng: &mut SmallRng, mutation: &Mutation) {
@@ -146,49 +231,62 @@ impl Habitat {
     pub fn mutate_memory_overwrite(&mut self, rng: &mut SmallRng, amount: u64) {
         for _ in 0..amount {
             let coords = self.get_random_coords(rng);
-            let location = self.get_mut(coords);
-            if let Some(computer) = &mut location.computer {
-                computer.mutate_memory_overwrite(rng);
-            }
+            self.mutate_at(coords, MutationKind::Overwrite, |computer| {
+                computer.mutate_memory_overwrite(rng)
+            });
         }
     }
 
     pub fn mutate_memory_insert(&mut self, rng: &mut SmallRng, amount: u64) {
         for _ in 0..amount {
             let coords = self.get_random_coords(rng);
-            let location = self.get_mut(coords);
-            if let Some(computer) = &mut location.computer {
-                computer.mutate_memory_insert(rng);
-            }
+            self.mutate_at(coords, MutationKind::Insert, |computer| {
+                computer.mutate_memory_insert(rng)
+            });
         }
     }
 
     pub fn mutate_memory_delete(&mut self, rng: &mut SmallRng, amount: u64) {
         for _ in 0..amount {
             let coords = self.get_random_coords(rng);
-            let location = self.get_mut(coords);
-            if let Some(computer) = &mut location.computer {
-                computer.mutate_memory_delete(rng);
-            }
+            self.mutate_at(coords, MutationKind::Delete, |computer| {
+                computer.mutate_memory_delete(rng)
+            });
         }
     }
 
     pub fn mutate_processor_stack(&mut self, rng: &mut SmallRng, amount: u64) {
         for _ in 0..amount {
             let coords = self.get_random_coords(rng);
-            let location = self.get_mut(coords);
-            if let Some(computer) = &mut location.computer {
-                computer.mutate_processors(rng);
-            }
+            self.mutate_at(coords, MutationKind::Stack, |computer| {
+                computer.mutate_processors(rng)
+            });
+        }
+    }
+
+    fn mutate_at(&mut self, coords: Coords, mutation: MutationKind, f: impl FnOnce(&mut Computer)) {
+        if let Some(computer) = &mut self.get_mut(coords).computer {
+            f(computer);
+            let computer_id = computer.id;
+            self.record(EventKind::Mutation {
+                coords,
+                computer_id,
+                mutation,
+            });
         }
     }
 
     pub fn death(&mut self, rng: &mut SmallRng, coords: Coords, death: &Death) -> bool {
         let location = self.get_mut(coords);
         if let Some(computer) = &mut location.computer {
-            if rng.gen_ratio(1, death.rate) || computer.memory.values.len() > death.memory_size {
-                location.resources += computer.resources + computer.memory.values.len() as u64;
-                location.computer = None;
+            let random = rng.gen_ratio(1, death.rate);
+            if random || computer.memory.values.len() > death.memory_size {
+                let cause = if random {
+                    DeathCause::Random
+                } else {
+                    DeathCause::MemorySize
+                };
+                self.remove(coords, cause);
                 return true;
             }
         }
@@ -196,16 +294,30 @@ impl Habitat {
     }
 
     pub fn die(&mut self, coords: Coords) {
+        self.remove(coords, DeathCause::Killed);
+    }
+
+    // the computer's resources go back to the location
+    fn remove(&mut self, coords: Coords, cause: DeathCause) {
         let location = self.get_mut(coords);
-        if let Some(computer) = &mut location.computer {
+        if let Some(computer) = location.computer.take() {
             location.resources += computer.resources + computer.memory.values.len() as u64;
-            location.computer = None;
+            self.record(EventKind::Death {
+                coords,
+                computer_id: computer.id,
+                cause,
+            });
         }
     }
 
     pub fn wipeout(&mut self, rng: &mut SmallRng, width: usize, height: usize) {
         let start_x = rng.gen_range(0..self.width);
         let start_y = rng.gen_range(0..self.height);
+        self.record(EventKind::Wipeout {
+            coords: (start_x, start_y),
+            width,
+            height,
+        });
         for y in start_y..start_y + height {
             for x in start_x..start_x + width {
                 let rx = x.rem_euclid(self.width);
@@ -279,9 +391,18 @@ impl Habitat {
     fn split(&mut self, coords: Coords, neighbor_coords: Coords, address: usize) {
         let computer = &mut self.get_mut(coords).computer;
         if let Some(computer) = computer {
-            let splitted = computer.split(address);
-            let neighbor_location = self.get_mut(neighbor_coords);
-            neighbor_location.computer = splitted;
+            let computer_id = computer.id;
+            if let Some(mut child) = computer.split(address) {
+                let child_id = self.new_id();
+                child.id = child_id;
+                self.get_mut(neighbor_coords).computer = Some(child);
+                self.record(EventKind::Birth {
+                    coords,
+                    computer_id,
+                    child_coords: neighbor_coords,
+                    child_id,
+                });
+            }
         }
     }
 
@@ -295,6 +416,13 @@ impl Habitat {
         let computer = &mut self.get_mut(coords).computer;
         if let Some(computer) = computer {
             computer.merge(neighbor_computer, max_processors);
+            let computer_id = computer.id;
+            self.record(EventKind::Merge {
+                coords,
+                computer_id,
+                neighbor_coords,
+                neighbor_id: neighbor_computer.id,
+            });
         }
         let neighbor_location = self.get_mut(neighbor_coords);
         neighbor_location.computer = None;
@@ -305,8 +433,14 @@ impl Habitat {
         if let Some(computer) = computer {
             // ugh, expensive to move around
             let copy = computer.clone();
+            let computer_id = copy.id;
             let neighbor_location = self.get_mut(neighbor_coords);
             neighbor_location.computer = Some(copy);
+            self.record(EventKind::Move {
+                from_coords: coords,
+                to_coords: neighbor_coords,
+                computer_id,
+            });
         }
         let location = self.get_mut(coords);
         location.computer = None;
@@ -322,6 +456,12 @@ impl Habitat {
             };
             computer.resources += amount;
             location.resources -= amount;
+            let computer_id = computer.id;
+            self.record(EventKind::Eat {
+                coords,
+                computer_id,
+                amount,
+            });
         }
     }
 
@@ -366,17 +506,43 @@ impl Habitat {
         (free, bound, memory)
     }
 
+    // the most common memory contents of all computers
+    pub fn dominant_genotype(&self) -> Option<Vec<u8>> {
+        let mut counts: FxHashMap<&[u8], u64> = FxHashMap::default();
+        for row in &self.rows {
+            for location in row {
+                if let Some(computer) = &location.computer {
+                    *counts.entry(&computer.memory.values).or_insert(0) += 1;
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .map(|(values, _)| values.to_vec())
+    }
+
     pub fn take_sample(
         &self,
         rng: &mut SmallRng,
         from_rect: &Rectangle,
         max_tries: u64,
+    ) -> Option<(Computer, Coords)> {
+        self.take_matching_sample(rng, from_rect, max_tries, |_| true)
+    }
+
+    // like take_sample, but only computers accepted by the predicate count
+    pub fn take_matching_sample(
+        &self,
+        rng: &mut SmallRng,
+        from_rect: &Rectangle,
+        max_tries: u64,
+        accept: impl Fn(&Computer) -> bool,
     ) -> Option<(Computer, Coords)> {
         for _ in 0..max_tries {
             let coords = from_rect.random_coords(rng);
-            if !self.is_empty(coords) {
-                let location = self.get(coords);
-                if let Some(computer) = &location.computer {
+            if let Some(computer) = &self.get(coords).computer {
+                if accept(computer) {
                     return Some((computer.clone(), coords));
                 }
             }
@@ -405,11 +571,13 @@ impl Habitat {
         from_rect: &Rectangle,
         to_rect: &Rectangle,
         destination: &Habitat,
+        accept: impl Fn(&Computer) -> bool,
     ) -> Option<(Coords, Coords, Computer)> {
         let destination_coords =
             destination.get_place_sample_coords(rng, to_rect, CONNECTION_SAMPLING_TRIES);
         if let Some(destination_coords) = destination_coords {
-            let computer = self.take_sample(rng, from_rect, CONNECTION_SAMPLING_TRIES);
+            let computer =
+                self.take_matching_sample(rng, from_rect, CONNECTION_SAMPLING_TRIES, accept);
             if let Some((computer, from_coords)) = computer {
                 return Some((from_coords, destination_coords, computer));
             }
@@ -417,7 +585,12 @@ impl Habitat {
         None
     }
 
-    pub fn disassemble(&self, assembler: &Assembler, x: usize, y: usize) -> Result<String, String> {
+    pub fn disassemble(
+        &self,
+        assembler: &Assembler,
+        x: usize,
+        y: usize,
+    ) -> Result<ComputerInspection, String> {
         if x >= self.width {
             return Err("x out of range".to_string());
         }
@@ -427,7 +600,7 @@ impl Habitat {
 
         let location = self.get((x, y));
         if let Some(computer) = &location.computer {
-            Ok(assembler.line_disassemble(&computer.memory.values))
+            Ok(ComputerInspection::new(computer, assembler))
         } else {
             Err("no computer".to_string())
         }
@@ -442,15 +615,17 @@ impl Location {
         }
     }
 
-    pub fn update(&mut self, rng: &mut SmallRng, config: &HabitatConfig) {
+    // returns how many instructions were executed
+    pub fn update(&mut self, rng: &mut SmallRng, config: &HabitatConfig) -> u64 {
         let mut eliminate_computer: bool = false;
+        let mut instructions = 0;
 
         if let Some(computer) = &mut self.computer {
             if computer.processors.is_empty() {
                 self.resources += computer.resources + computer.memory.values.len() as u64;
                 eliminate_computer = true;
             } else {
-                computer.execute(
+                instructions = computer.execute(
                     rng,
                     config.instructions_per_update,
                     config.max_processors,
@@ -461,6 +636,7 @@ impl Location {
         if eliminate_computer {
             self.computer = None;
         }
+        instructions
     }
 }
 
@@ -486,7 +662,7 @@ mod tests {
     #[test]
     fn test_want_split_no_obstructions() {
         let mut habitat = Habitat::new(5, 5, 5);
-        let mut location = habitat.get_mut((2, 2));
+        let location = habitat.get_mut((2, 2));
         location.computer = Some(Computer::new(1, 1));
         if let Some(computer) = &mut location.computer {
             computer.wants.split.want((Direction::North, 1));
@@ -499,12 +675,12 @@ mod tests {
     #[test]
     fn test_want_split_with_obstruction() {
         let mut habitat = Habitat::new(5, 5, 5);
-        let mut location = habitat.get_mut((2, 2));
+        let location = habitat.get_mut((2, 2));
         location.computer = Some(Computer::new(1, 1));
         if let Some(computer) = &mut location.computer {
             computer.wants.split.want((Direction::North, 1));
         }
-        let mut location_north = habitat.get_mut((2, 1));
+        let location_north = habitat.get_mut((2, 1));
         location_north.computer = Some(Computer::new(1, 1));
 
         let mut rng = SmallRng::from_seed([0; 32]);
@@ -514,7 +690,7 @@ mod tests {
     #[test]
     fn test_want_merge_no_neighbor() {
         let mut habitat = Habitat::new(5, 5, 5);
-        let mut location = habitat.get_mut((2, 2));
+        let location = habitat.get_mut((2, 2));
         location.computer = Some(Computer::new(1, 1));
         if let Some(computer) = &mut location.computer {
             computer.wants.merge.want(Direction::North);
@@ -526,12 +702,12 @@ mod tests {
     #[test]
     fn test_want_merge_with_unblocked_neighbor() {
         let mut habitat = Habitat::new(5, 5, 5);
-        let mut location = habitat.get_mut((2, 2));
+        let location = habitat.get_mut((2, 2));
         location.computer = Some(Computer::new(1, 1));
         if let Some(computer) = &mut location.computer {
             computer.wants.merge.want(Direction::North);
         }
-        let mut location_north = habitat.get_mut((2, 1));
+        let location_north = habitat.get_mut((2, 1));
         location_north.computer = Some(Computer::new(1, 1));
 
         let mut rng = SmallRng::from_seed([0; 32]);
@@ -541,13 +717,13 @@ mod tests {
     #[test]
     fn test_want_merge_with_blocked_neighbor() {
         let mut habitat = Habitat::new(5, 5, 5);
-        let mut location = habitat.get_mut((2, 2));
+        let location = habitat.get_mut((2, 2));
         location.computer = Some(Computer::new(1, 1));
         if let Some(computer) = &mut location.computer {
             computer.wants.merge.want(Direction::North);
         }
 
-        let mut location_north = habitat.get_mut((2, 1));
+        let location_north = habitat.get_mut((2, 1));
         location_north.computer = Some(Computer::new(1, 1));
         let location_north = habitat.get_mut((2, 1));
         if let Some(computer) = &mut location_north.computer {
@@ -561,14 +737,14 @@ mod tests {
     #[test]
     fn test_want_merge_overcome_blocked_neighbor() {
         let mut habitat = Habitat::new(5, 5, 5);
-        let mut location = habitat.get_mut((2, 2));
+        let location = habitat.get_mut((2, 2));
         location.computer = Some(Computer::new(1, 1));
         if let Some(computer) = &mut location.computer {
             computer.wants.merge.want(Direction::North);
             computer.wants.merge.want(Direction::North);
         }
 
-        let mut location_north = habitat.get_mut((2, 1));
+        let location_north = habitat.get_mut((2, 1));
         location_north.computer = Some(Computer::new(1, 1));
         let location_north = habitat.get_mut((2, 1));
         if let Some(computer) = &mut location_north.computer {
@@ -582,13 +758,13 @@ mod tests {
     #[test]
     fn test_want_merge_with_blocked_neighbor_but_not_in_right_direction() {
         let mut habitat = Habitat::new(5, 5, 5);
-        let mut location = habitat.get_mut((2, 2));
+        let location = habitat.get_mut((2, 2));
         location.computer = Some(Computer::new(1, 1));
         if let Some(computer) = &mut location.computer {
             computer.wants.merge.want(Direction::North);
         }
 
-        let mut location_north = habitat.get_mut((2, 1));
+        let location_north = habitat.get_mut((2, 1));
         location_north.computer = Some(Computer::new(1, 1));
         let location_north = habitat.get_mut((2, 1));
         if let Some(computer) = &mut location_north.computer {
@@ -598,4 +774,70 @@ mod tests {
         let mut rng = SmallRng::from_seed([0; 32]);
         assert_eq!(habitat.want_merge((2, 2), &mut rng), Some((2, 1)));
     }
+
+    #[test]
+    fn test_split_events() {
+        let mut habitat = Habitat::new(5, 5, 5);
+        habitat.record_events(true);
+        let mut computer = Computer::new(4, 4);
+        computer.add_processor(0);
+        let computer_id = habitat.set((2, 2), computer);
+        habitat.split((2, 2), (2, 1), 2);
+        habitat.die((2, 1));
+        let events = habitat.take_events();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            events[0],
+            EventKind::Arrival {
+                coords: (2, 2),
+                computer_id: 1
+            }
+        ));
+        match events[1] {
+            EventKind::Birth {
+                computer_id: parent_id,
+                child_coords,
+                child_id,
+                ..
+            } => {
+                assert_eq!(parent_id, computer_id);
+                assert_eq!(child_coords, (2, 1));
+                assert_eq!(child_id, 2);
+            }
+            ref kind => panic!("unexpected event {:?}", kind),
+        }
+        assert!(matches!(
+            events[2],
+            EventKind::Death {
+                computer_id: 2,
+                cause: DeathCause::Killed,
+                ..
+            }
+        ));
+        assert!(habitat.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_no_events_unless_recording() {
+        let mut habitat = Habitat::new(5, 5, 5);
+        habitat.set((2, 2), Computer::new(1, 1));
+        habitat.die((2, 2));
+        assert!(habitat.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_dominant_genotype() {
+        let mut habitat = Habitat::new(5, 5, 5);
+        assert_eq!(habitat.dominant_genotype(), None);
+
+        let mut computer = Computer::new(2, 1);
+        computer.memory.values = vec![1, 2];
+        habitat.set((0, 0), computer.clone());
+        habitat.set((1, 0), computer);
+        let mut computer = Computer::new(2, 1);
+        computer.memory.values = vec![3, 4];
+        habitat.set((2, 0), computer);
+
+        assert_eq!(habitat.dominant_genotype(), Some(vec![1, 2]));
+    }
 }
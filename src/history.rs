@@ -0,0 +1,172 @@
+use crate::habitat::Habitat;
+use crate::ticks::Ticks;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+// an island's stats over time, for charts. it's saved in dumps, so it goes
+// back to the start of the run until it's full; then the oldest samples go
+
+pub const DEFAULT_HISTORY_TICKS: Ticks = Ticks(100000);
+pub const DEFAULT_HISTORY_SIZE: usize = 10000;
+
+// how the world samples the history of its islands, including those added
+// later. None keeps what the island has
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryConfig {
+    pub frequency: Option<Ticks>,
+    pub size: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct History {
+    // ticks between samples
+    pub frequency: Ticks,
+    // the most samples kept
+    pub size: usize,
+    samples: VecDeque<HistorySample>,
+}
+
+// what the history endpoint returns
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryInfo {
+    pub island_id: usize,
+    pub frequency: Ticks,
+    pub samples: Vec<HistorySample>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySample {
+    pub ticks: u64,
+    pub computers: u64,
+    pub processors: u64,
+    pub free_resources: u64,
+    pub bound_resources: u64,
+    pub memory_resources: u64,
+}
+
+impl Default for History {
+    fn default() -> History {
+        History {
+            frequency: DEFAULT_HISTORY_TICKS,
+            size: DEFAULT_HISTORY_SIZE,
+            samples: VecDeque::new(),
+        }
+    }
+}
+
+impl History {
+    pub fn configure(&mut self, frequency: Option<Ticks>, size: Option<usize>) {
+        if let Some(frequency) = frequency {
+            // samples at different spacings can't be charted together
+            if frequency != self.frequency {
+                self.samples.clear();
+            }
+            self.frequency = frequency;
+        }
+        if let Some(size) = size {
+            self.size = size;
+        }
+        while self.samples.len() > self.size {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    // after every update of the island
+    pub fn update(&mut self, ticks: Ticks, habitat: &Habitat) {
+        if self.frequency.0 == 0 || self.size == 0 || !ticks.is_at(self.frequency) {
+            return;
+        }
+        if self.samples.len() >= self.size {
+            self.samples.pop_front();
+        }
+        let (free_resources, bound_resources, memory_resources) = habitat.resources_amounts();
+        self.samples.push_back(HistorySample {
+            ticks: ticks.0,
+            computers: habitat.computers_amount(),
+            processors: habitat.processors_amount(),
+            free_resources,
+            bound_resources,
+            memory_resources,
+        });
+    }
+
+    // the samples from and to these ticks, inclusive. if there are more than
+    // max_samples, consecutive ones are averaged
+    pub fn query(
+        &self,
+        from: Option<u64>,
+        to: Option<u64>,
+        max_samples: Option<usize>,
+    ) -> Vec<HistorySample> {
+        let samples: Vec<&HistorySample> = self
+            .samples
+            .iter()
+            .filter(|sample| from.is_none_or(|from| sample.ticks >= from))
+            .filter(|sample| to.is_none_or(|to| sample.ticks <= to))
+            .collect();
+        let max_samples = max_samples.unwrap_or(usize::MAX).max(1);
+        let per_sample = samples.len().div_ceil(max_samples).max(1);
+        samples.chunks(per_sample).map(mean).collect()
+    }
+}
+
+// at the ticks of the first sample
+fn mean(samples: &[&HistorySample]) -> HistorySample {
+    let amount = samples.len() as u64;
+    let average = |value: fn(&HistorySample) -> u64| {
+        samples.iter().map(|sample| value(sample)).sum::<u64>() / amount
+    };
+    HistorySample {
+        ticks: samples[0].ticks,
+        computers: average(|sample| sample.computers),
+        processors: average(|sample| sample.processors),
+        free_resources: average(|sample| sample.free_resources),
+        bound_resources: average(|sample| sample.bound_resources),
+        memory_resources: average(|sample| sample.memory_resources),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::Computer;
+
+    #[test]
+    fn test_history() {
+        let mut habitat = Habitat::new(2, 2, 10);
+        let mut history = History::default();
+        history.configure(Some(Ticks(10)), Some(3));
+        for ticks in 1..=50 {
+            if ticks == 25 {
+                habitat.set((0, 0), Computer::new(4, 2));
+            }
+            history.update(Ticks(ticks), &habitat);
+        }
+        let ticks = |samples: Vec<HistorySample>| -> Vec<u64> {
+            samples.iter().map(|sample| sample.ticks).collect()
+        };
+        // the oldest samples are gone
+        assert_eq!(ticks(history.query(None, None, None)), vec![30, 40, 50]);
+        assert_eq!(ticks(history.query(Some(35), Some(40), None)), vec![40]);
+
+        let samples = history.query(None, None, Some(2));
+        assert_eq!(ticks(samples.clone()), vec![30, 50]);
+        assert_eq!(samples[0].computers, 1);
+        assert_eq!(samples[0].memory_resources, 4);
+
+        history.configure(None, Some(1));
+        assert_eq!(ticks(history.query(None, None, None)), vec![50]);
+
+        // the same frequency keeps the samples, another one starts over
+        history.configure(Some(Ticks(10)), None);
+        assert_eq!(history.query(None, None, None).len(), 1);
+        history.configure(Some(Ticks(5)), None);
+        assert!(history.query(None, None, None).is_empty());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::fill::Rgb;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{Result, Write};
+
+// just enough image writing for frames: binary PPM, and PNG
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, background: Rgb) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![background; width * height],
+        }
+    }
+
+    pub fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                self.pixels[row * self.width + column] = color;
+            }
+        }
+    }
+
+    pub fn write_ppm(&self, writer: &mut impl Write) -> Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.rgb_bytes())
+    }
+
+    pub fn write_png(&self, writer: &mut impl Write) -> Result<()> {
+        writer.write_all(&PNG_SIGNATURE)?;
+        let mut header = Vec::new();
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8 bit truecolor, default compression and filtering, no interlace
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        write_chunk(writer, b"IHDR", &header)?;
+
+        // every row starts with its filter type, which is none. habitats
+        // have large areas of the same color, which compress well anyway
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+        for row in self.rgb_bytes().chunks(self.width * 3) {
+            encoder.write_all(&[0])?;
+            encoder.write_all(row)?;
+        }
+        write_chunk(writer, b"IDAT", &encoder.finish()?)?;
+        write_chunk(writer, b"IEND", &[])
+    }
+
+    fn rgb_bytes(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&Rgb(r, g, b)| [r, g, b])
+            .collect()
+    }
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    writer.write_all(&crc.finalize().to_be_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn test_ppm() {
+        let mut image = Image::new(2, 1, Rgb(0, 0, 0));
+        image.fill_rect(1, 0, 5, 5, Rgb(1, 2, 3));
+        let mut ppm = Vec::new();
+        image.write_ppm(&mut ppm).unwrap();
+        assert_eq!(ppm, b"P6\n2 1\n255\n\x00\x00\x00\x01\x02\x03");
+    }
+
+    #[test]
+    fn test_png() {
+        let mut image = Image::new(300, 300, Rgb(1, 2, 3));
+        image.fill_rect(1, 0, 1, 1, Rgb(4, 5, 6));
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        assert_eq!(png[..8], PNG_SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        let idat_length = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        // a single color compresses to far less than the raw image
+        let raw_length = 300 * (300 * 3 + 1);
+        assert!(idat_length < raw_length / 10);
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&png[41..41 + idat_length])
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(raw.len(), raw_length);
+        assert_eq!(raw[..7], [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(raw[301 * 3 - 2..301 * 3 + 2], [0, 1, 2, 3]);
+        // IEND always has the same checksum
+        assert_eq!(png[png.len() - 8..], *b"IEND\xae\x42\x60\x82");
+    }
+}
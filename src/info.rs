@@ -1,52 +1,171 @@
-use crate::computer::Computer;
+use crate::computer::{Computer, Genotype};
 use crate::habitat::Location;
 use crate::island::Island;
+use crate::view::{OverviewInfo, OverviewKey, ViewInfo};
 use crate::world::WorldState;
 use serde_derive::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
 // info useful for the UI
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct WorldStateInfo {
-    islands: Vec<IslandInfo>,
-    observed_island_id: usize,
-    locations: Vec<Vec<LocationInfo>>,
+    pub islands: Vec<IslandInfo>,
+    pub observed_island_id: usize,
+    // every location of the observed island, unless it's a view
+    pub locations: Vec<Vec<LocationInfo>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view: Option<ViewInfo>,
+    // of the observed island, worked out once for every client of a view
+    #[serde(skip)]
+    pub overviews: Vec<Arc<OverviewInfo>>,
+}
+
+// whether anyone is after every location of the observed island. for large
+// islands that's expensive, so otherwise they're left out. the same goes for
+// the genotypes of computers, which take hashing their memory, and for the
+// overviews of views
+#[derive(Debug, Default)]
+pub struct FrameDemand {
+    full_frames: AtomicUsize,
+    genotypes: AtomicUsize,
+    overviews: Mutex<HashMap<OverviewKey, usize>>,
+}
+
+// full frames are wanted until this is dropped
+pub struct FullFrames(Arc<FrameDemand>);
+
+// genotypes are wanted until this is dropped
+pub struct Genotypes(Arc<FrameDemand>);
+
+// the overview is wanted until this is dropped
+pub struct Overview {
+    frame_demand: Arc<FrameDemand>,
+    pub key: OverviewKey,
+}
+
+impl FrameDemand {
+    pub fn want_full_frames(self: &Arc<Self>) -> FullFrames {
+        self.full_frames.fetch_add(1, Ordering::Relaxed);
+        FullFrames(Arc::clone(self))
+    }
+
+    pub fn wants_full_frames(&self) -> bool {
+        self.full_frames.load(Ordering::Relaxed) > 0
+    }
+
+    pub fn want_genotypes(self: &Arc<Self>) -> Genotypes {
+        self.genotypes.fetch_add(1, Ordering::Relaxed);
+        Genotypes(Arc::clone(self))
+    }
+
+    pub fn wants_genotypes(&self) -> bool {
+        self.genotypes.load(Ordering::Relaxed) > 0
+    }
+
+    pub fn want_overview(self: &Arc<Self>, key: OverviewKey) -> Overview {
+        *self.overviews.lock().unwrap().entry(key).or_insert(0) += 1;
+        Overview {
+            frame_demand: Arc::clone(self),
+            key,
+        }
+    }
+
+    pub fn wanted_overviews(&self) -> Vec<OverviewKey> {
+        self.overviews.lock().unwrap().keys().copied().collect()
+    }
+}
+
+impl Drop for FullFrames {
+    fn drop(&mut self) {
+        self.0.full_frames.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Genotypes {
+    fn drop(&mut self) {
+        self.0.genotypes.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Overview {
+    fn drop(&mut self) {
+        let mut overviews = self.frame_demand.overviews.lock().unwrap();
+        if let Some(wanted) = overviews.get_mut(&self.key) {
+            *wanted -= 1;
+            if *wanted == 0 {
+                overviews.remove(&self.key);
+            }
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IslandInfo {
-    width: usize,
-    height: usize,
-    total_free_resources: u64,
-    total_bound_resources: u64,
-    total_memory_resources: u64,
-    total_computers: u64,
-    total_processors: u64,
+    pub width: usize,
+    pub height: usize,
+    pub total_free_resources: u64,
+    pub total_bound_resources: u64,
+    pub total_memory_resources: u64,
+    pub total_computers: u64,
+    pub total_processors: u64,
+    pub paused: bool,
+    pub speed_limit: Option<f64>,
+    pub ticks: u64,
+    pub ticks_per_second: f64,
+    pub instructions_per_second: f64,
+    // fraction of time spent waiting for the island lock
+    pub mutex_wait: f64,
 }
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct LocationInfo {
-    free_resources: u64,
-    computer: Option<ComputerInfo>,
+    pub free_resources: u64,
+    pub computer: Option<ComputerInfo>,
 }
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ComputerInfo {
-    memory_size: usize,
-    processors: usize,
-    bound_resources: u64,
+    pub memory_size: usize,
+    pub processors: usize,
+    pub bound_resources: u64,
+    // only used for coloring in the terminal and frames, the web UI doesn't
+    // need it. left out unless asked for, as it takes hashing the memory
+    #[serde(skip)]
+    pub genotype: Option<Genotype>,
 }
 
-impl From<&WorldState> for WorldStateInfo {
-    fn from(world_state: &WorldState) -> WorldStateInfo {
+impl WorldStateInfo {
+    pub fn without_locations(world_state: &WorldState) -> WorldStateInfo {
         let mut islands = Vec::new();
         for island in world_state.get_islands() {
             islands.push((&*island.lock().unwrap()).into());
         }
+        WorldStateInfo {
+            locations: Vec::new(),
+            islands,
+            observed_island_id: world_state.get_observed_id(),
+            view: None,
+            overviews: Vec::new(),
+        }
+    }
+}
+
+impl From<&WorldState> for WorldStateInfo {
+    fn from(world_state: &WorldState) -> WorldStateInfo {
+        WorldStateInfo::new(world_state, false)
+    }
+}
+
+impl WorldStateInfo {
+    pub fn new(world_state: &WorldState, with_genotypes: bool) -> WorldStateInfo {
+        let mut info = WorldStateInfo::without_locations(world_state);
         let mut locations: Vec<Vec<LocationInfo>> = Vec::new();
 
         let observed = world_state.get_observed();
@@ -57,16 +176,13 @@ impl From<&WorldState> for WorldStateInfo {
         for row in &habitat.rows {
             let mut row_locations: Vec<LocationInfo> = Vec::new();
             for location in row {
-                row_locations.push(location.into());
+                row_locations.push(LocationInfo::new(location, with_genotypes));
             }
             locations.push(row_locations);
         }
 
-        WorldStateInfo {
-            locations,
-            islands,
-            observed_island_id: world_state.get_observed_id(),
-        }
+        info.locations = locations;
+        info
     }
 }
 
@@ -77,16 +193,6 @@ impl From<&Island> for IslandInfo {
         let total_computers = island.habitat.computers_amount();
         let total_processors = island.habitat.processors_amount();
 
-        let mut locations: Vec<Vec<LocationInfo>> = Vec::new();
-
-        for row in &island.habitat.rows {
-            let mut row_locations: Vec<LocationInfo> = Vec::new();
-            for location in row {
-                row_locations.push(location.into())
-            }
-            locations.push(row_locations);
-        }
-
         IslandInfo {
             width: island.habitat.width,
             height: island.habitat.height,
@@ -95,27 +201,47 @@ impl From<&Island> for IslandInfo {
             total_memory_resources,
             total_computers,
             total_processors,
+            paused: island.control.paused,
+            speed_limit: island.control.speed_limit,
+            ticks: island.ticks.0,
+            ticks_per_second: island.metrics.ticks_per_second,
+            instructions_per_second: island.metrics.instructions_per_second,
+            mutex_wait: island.metrics.mutex_wait,
         }
     }
 }
 
 impl From<&Location> for LocationInfo {
     fn from(location: &Location) -> LocationInfo {
-        let computer: Option<ComputerInfo> =
-            location.computer.as_ref().map(|computer| computer.into());
+        LocationInfo::new(location, false)
+    }
+}
+
+impl LocationInfo {
+    pub fn new(location: &Location, with_genotype: bool) -> LocationInfo {
         LocationInfo {
             free_resources: location.resources,
-            computer,
+            computer: location
+                .computer
+                .as_ref()
+                .map(|computer| ComputerInfo::new(computer, with_genotype)),
         }
     }
 }
 
 impl From<&Computer> for ComputerInfo {
     fn from(computer: &Computer) -> ComputerInfo {
+        ComputerInfo::new(computer, false)
+    }
+}
+
+impl ComputerInfo {
+    pub fn new(computer: &Computer, with_genotype: bool) -> ComputerInfo {
         ComputerInfo {
             memory_size: computer.memory.values.len(),
             processors: computer.processors.len(),
             bound_resources: computer.resources,
+            genotype: with_genotype.then(|| computer.genotype()),
         }
     }
 }
@@ -0,0 +1,207 @@
+use crate::assembler::Assembler;
+use crate::computer::{Computer, Genotype};
+use crate::direction::Direction;
+use crate::processor::{Processor, HEADS_AMOUNT};
+use crate::want::Counts;
+use serde_derive::Serialize;
+use std::fmt::Write;
+
+// what a single computer is doing, for the UI sidebar
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ComputerInspection {
+    pub id: u64,
+    pub genotype: Genotype,
+    pub resources: u64,
+    pub memory_size: usize,
+    // the listing with addresses, and processor ips and heads marked
+    pub code: String,
+    pub lines: Vec<LineInspection>,
+    pub processors: Vec<ProcessorInspection>,
+    pub wants: WantsInspection,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LineInspection {
+    pub address: usize,
+    pub instruction: String,
+    // the processors whose ip is here
+    pub ips: Vec<usize>,
+    pub heads: Vec<HeadMark>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadMark {
+    pub processor: usize,
+    pub head: usize,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessorInspection {
+    pub ip: usize,
+    pub current_head: usize,
+    pub heads: Vec<Option<usize>>,
+    pub stack: Vec<u64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Want<T> {
+    pub value: T,
+    pub strength: i32,
+}
+
+// the wants of the last update, which are what the habitat acted upon
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WantsInspection {
+    pub start: Vec<Want<usize>>,
+    pub shrink: Vec<Want<u64>>,
+    pub grow: Vec<Want<u64>>,
+    pub eat: Vec<Want<u64>>,
+    pub split: Vec<Want<(Direction, usize)>>,
+    pub merge: Vec<Want<Direction>>,
+    #[serde(rename = "move")]
+    pub move_: Vec<Want<Direction>>,
+    pub block_merge: Vec<Want<Direction>>,
+}
+
+impl ComputerInspection {
+    pub fn new(computer: &Computer, assembler: &Assembler) -> ComputerInspection {
+        let processors: Vec<ProcessorInspection> =
+            computer.processors.iter().map(|p| p.into()).collect();
+        let mut lines: Vec<LineInspection> = assembler
+            .disassemble_to_words(&computer.memory.values)
+            .into_iter()
+            .enumerate()
+            .map(|(address, instruction)| LineInspection {
+                address,
+                instruction,
+                ips: Vec::new(),
+                heads: Vec::new(),
+            })
+            .collect();
+        for (i, processor) in processors.iter().enumerate() {
+            if let Some(line) = lines.get_mut(processor.ip) {
+                line.ips.push(i);
+            }
+            for (head, address) in processor.heads.iter().enumerate() {
+                if let Some(line) = address.and_then(|address| lines.get_mut(address)) {
+                    line.heads.push(HeadMark { processor: i, head });
+                }
+            }
+        }
+        ComputerInspection {
+            id: computer.id,
+            genotype: computer.genotype(),
+            resources: computer.resources,
+            memory_size: computer.memory.values.len(),
+            code: listing(&lines),
+            lines,
+            processors,
+            wants: WantsInspection::from(computer),
+        }
+    }
+}
+
+// one instruction per line, like:
+//
+//        12  SPLIT  # ip 0, head 0.1
+fn listing(lines: &[LineInspection]) -> String {
+    let mut listing = Vec::new();
+    for line in lines {
+        let mut code = format!("{:5}  {}", line.address, line.instruction);
+        let marks: Vec<String> = line
+            .ips
+            .iter()
+            .map(|processor| format!("ip {}", processor))
+            .chain(
+                line.heads
+                    .iter()
+                    .map(|mark| format!("head {}.{}", mark.processor, mark.head)),
+            )
+            .collect();
+        if !marks.is_empty() {
+            write!(code, "  # {}", marks.join(", ")).unwrap();
+        }
+        listing.push(code);
+    }
+    listing.join("\n")
+}
+
+impl From<&Processor> for ProcessorInspection {
+    fn from(processor: &Processor) -> ProcessorInspection {
+        ProcessorInspection {
+            ip: processor.ip,
+            current_head: processor.current_head,
+            heads: (0..HEADS_AMOUNT).map(|nr| processor.get_head(nr)).collect(),
+            stack: processor.current_stack().to_vec(),
+        }
+    }
+}
+
+fn wants<T: Eq + Copy + Default>(counts: &Counts<T>) -> Vec<Want<T>> {
+    counts
+        .get_strength()
+        .map(|(value, strength)| Want { value, strength })
+        .collect()
+}
+
+impl From<&Computer> for WantsInspection {
+    fn from(computer: &Computer) -> WantsInspection {
+        let w = &computer.wants;
+        WantsInspection {
+            start: wants(&w.start),
+            shrink: wants(&w.shrink),
+            grow: wants(&w.grow),
+            eat: wants(&w.eat),
+            split: wants(&w.split),
+            merge: wants(&w.merge),
+            move_: wants(&w.move_),
+            block_merge: wants(&w.block_merge),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspection() {
+        let assembler = Assembler::new();
+        let mut computer = Computer::new(4, 10);
+        assembler
+            .assemble("N1 N2 ADD EAT", &mut computer.memory, 0)
+            .unwrap();
+        let mut processor = Processor::new(2);
+        processor.push(3);
+        processor.set_current_head_value(3);
+        computer.processors.push(processor);
+        computer.processors.push(Processor::new(2));
+        computer.wants.eat.want(5);
+
+        let inspection = ComputerInspection::new(&computer, &assembler);
+        assert_eq!(inspection.memory_size, 4);
+        assert_eq!(inspection.genotype, computer.genotype());
+        assert_eq!(inspection.processors[0].stack, vec![3]);
+        assert_eq!(inspection.processors[0].heads[0], Some(3));
+        assert_eq!(inspection.lines[2].ips, vec![0, 1]);
+        assert_eq!(
+            inspection.lines[3].heads,
+            vec![HeadMark {
+                processor: 0,
+                head: 0
+            }]
+        );
+        assert_eq!(inspection.wants.eat[0].value, 5);
+        assert_eq!(
+            inspection.code,
+            "    0  N1\n    1  N2\n    2  ADD  # ip 0, ip 1\n    3  EAT  # head 0.0"
+        );
+    }
+}
@@ -280,7 +280,7 @@ impl Instruction {
             // Heads
             Instruction::HEAD => {
                 let head_nr = processor.pop_head_nr();
-                processor.current_head = head_nr as usize;
+                processor.current_head = head_nr;
             }
             Instruction::ADDR => {
                 processor.set_current_head_value(processor.ip);
@@ -314,11 +314,7 @@ impl Instruction {
                 match current_address {
                     Some(address) => match processor.get_head(head_nr) {
                         Some(other_address) => {
-                            let distance = if address > other_address {
-                                address - other_address
-                            } else {
-                                other_address - address
-                            };
+                            let distance = address.abs_diff(other_address);
                             processor.push(distance as u64);
                         }
                         None => {
@@ -350,7 +346,7 @@ impl Instruction {
                             // truncate
                             value as u8
                         };
-                        memory.values[address] = constrained_value;
+                        memory.write(address, constrained_value);
                     }
                     None => {
                         // no write out of bounds
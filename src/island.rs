@@ -1,21 +1,102 @@
+use crate::computer::{Computer, Genotype};
+use crate::events::Event;
+use crate::history::History;
+use crate::memory::Memory;
+use crate::observer::Observers;
 use crate::rectangle::Rectangle;
 use crate::{
-    habitat::{Habitat, HabitatConfig},
+    habitat::{Habitat, HabitatConfig, Mutation},
     ticks::Ticks,
 };
+use clap::ValueEnum;
 use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
 use serde_derive::{Deserialize, Serialize};
 use serde_with::serde_as;
 use std::time::Duration;
 
+const SEED_SAMPLING_TRIES: u64 = 2u64.pow(5);
+
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Connection {
     pub from_rect: Rectangle,
     pub to_rect: Rectangle,
     pub to_id: usize,
-    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
-    pub transmit_frequency: Duration,
+    // either seconds between migrations, or ticks of the source island
+    #[serde_as(as = "Option<serde_with::DurationSeconds<u64>>")]
+    #[serde(default)]
+    pub transmit_frequency: Option<Duration>,
+    #[serde(default)]
+    pub transmit_ticks: Option<Ticks>,
+    // how many computers migrate each time
+    #[serde(default = "default_migration_amount")]
+    pub amount: usize,
+    #[serde(default)]
+    pub mode: MigrationMode,
+    // only computers passing the filter migrate
+    #[serde(default)]
+    pub filter: Option<MigrationFilter>,
+    // applied to migrants in transit
+    #[serde(default)]
+    pub mutation: Option<Mutation>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct MigrationFilter {
+    #[serde(default)]
+    pub min_memory_size: Option<usize>,
+    #[serde(default)]
+    pub max_memory_size: Option<usize>,
+    #[serde(default)]
+    pub min_processors: Option<usize>,
+    #[serde(default)]
+    pub max_processors: Option<usize>,
+    #[serde(default)]
+    pub min_resources: Option<u64>,
+    // genotypes as shown in the migration log
+    #[serde(default)]
+    pub allow: Option<Vec<Genotype>>,
+    #[serde(default)]
+    pub deny: Vec<Genotype>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationMode {
+    // the migrant leaves the source island
+    #[default]
+    Move,
+    // the source island keeps the original
+    Copy,
+}
+
+fn default_migration_amount() -> usize {
+    1
+}
+
+impl MigrationFilter {
+    pub fn accepts(&self, computer: &Computer) -> bool {
+        let memory_size = computer.memory.values.len();
+        let processors = computer.processors.len();
+        let in_range = |value, min: Option<usize>, max: Option<usize>| {
+            min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+        };
+        if !in_range(memory_size, self.min_memory_size, self.max_memory_size)
+            || !in_range(processors, self.min_processors, self.max_processors)
+            || computer.resources < self.min_resources.unwrap_or(0)
+        {
+            return false;
+        }
+        if self.allow.is_none() && self.deny.is_empty() {
+            return true;
+        }
+        let genotype = computer.genotype();
+        self.allow
+            .as_ref()
+            .is_none_or(|allow| allow.contains(&genotype))
+            && !self.deny.contains(&genotype)
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -25,12 +106,170 @@ pub struct Disaster {
     pub height: usize,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// where to place copies of a seed program; by default in the whole habitat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SeedPattern {
+    // random empty locations
+    Scatter {
+        amount: usize,
+        rect: Option<Rectangle>,
+    },
+    // evenly spaced locations, skipping those already taken
+    Grid {
+        columns: usize,
+        rows: usize,
+        rect: Option<Rectangle>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Immigration {
+    pub computer: Computer,
+    pub pattern: SeedPattern,
+    pub frequency: Ticks,
+}
+
+// where computers come from when an island goes extinct
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExtinctionPolicy {
+    // the seed computers the island got from the topology
+    Seed,
+    // the last dominant genotype on the island
+    Genebank,
+    // a computer sampled from a connected island
+    Neighbor,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Extinction {
+    pub policy: ExtinctionPolicy,
+    #[serde_as(as = "serde_with::DurationSeconds<u64>")]
+    pub check_frequency: Duration,
+    pub pattern: SeedPattern,
+    // resources for computers created from the genebank
+    pub resources: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Island {
     pub habitat: Habitat,
     config: HabitatConfig,
     pub disaster: Option<Disaster>,
     pub connections: Vec<Connection>,
+    #[serde(default)]
+    pub immigrations: Vec<Immigration>,
+    #[serde(default)]
+    pub extinction: Option<Extinction>,
+    #[serde(default)]
+    pub seeds: Vec<Computer>,
+    #[serde(default)]
+    pub genebank: Option<Vec<u8>>,
+    #[serde(default)]
+    pub ticks: Ticks,
+    #[serde(default)]
+    pub history: History,
+    // its place in the world, kept up to date by the world
+    #[serde(skip)]
+    pub id: usize,
+    // set when the island is removed from the world, so its thread stops
+    #[serde(skip)]
+    pub retired: bool,
+    #[serde(skip)]
+    pub control: IslandControl,
+    #[serde(skip)]
+    pub metrics: IslandMetrics,
+    // recorded during updates, waiting to be published
+    #[serde(skip)]
+    events: Vec<Event>,
+    #[serde(skip)]
+    pub observers: Observers,
+}
+
+// how fast an island runs, measured by its thread
+#[derive(Debug, Clone, Default)]
+pub struct IslandMetrics {
+    // since the island started running
+    pub instructions: u64,
+    pub ticks_per_second: f64,
+    pub instructions_per_second: f64,
+    // the fraction of time the island's thread waits for its lock
+    pub mutex_wait: f64,
+}
+
+// lets an island be paused, stepped and slowed down on its own
+#[derive(Debug, Clone, Default)]
+pub struct IslandControl {
+    pub paused: bool,
+    // ticks still to run while paused
+    pub steps: u64,
+    // in ticks per second
+    pub speed_limit: Option<f64>,
+}
+
+impl IslandControl {
+    // whether the island should run another tick
+    pub fn take_tick(&mut self) -> bool {
+        if !self.paused {
+            return true;
+        }
+        if self.steps > 0 {
+            self.steps -= 1;
+            return true;
+        }
+        false
+    }
+}
+
+// the parts of an island that can be changed after it was created
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IslandSettings {
+    pub config: HabitatConfig,
+    pub disaster: Option<Disaster>,
+    pub connections: Vec<Connection>,
+    pub extinction: Option<Extinction>,
+}
+
+impl SeedPattern {
+    pub fn rect(&self) -> Option<&Rectangle> {
+        match self {
+            SeedPattern::Scatter { rect, .. } => rect.as_ref(),
+            SeedPattern::Grid { rect, .. } => rect.as_ref(),
+        }
+    }
+
+    // place copies of computer, returns how many were placed
+    pub fn place(&self, habitat: &mut Habitat, computer: &Computer, rng: &mut SmallRng) -> usize {
+        let rect = self
+            .rect()
+            .cloned()
+            .unwrap_or_else(|| Rectangle::new(0, 0, habitat.width, habitat.height));
+        let mut placed = 0;
+        match self {
+            SeedPattern::Scatter { amount, .. } => {
+                for _ in 0..*amount {
+                    match habitat.get_place_sample_coords(rng, &rect, SEED_SAMPLING_TRIES) {
+                        Some(coords) => {
+                            habitat.set(coords, computer.clone());
+                            placed += 1;
+                        }
+                        None => break,
+                    }
+                }
+            }
+            SeedPattern::Grid { columns, rows, .. } => {
+                for coords in rect.grid_coords(*columns, *rows) {
+                    if habitat.is_empty(coords) {
+                        habitat.set(coords, computer.clone());
+                        placed += 1;
+                    }
+                }
+            }
+        }
+        placed
+    }
 }
 
 impl Island {
@@ -39,17 +278,73 @@ impl Island {
         config: HabitatConfig,
         disaster: Option<Disaster>,
         connections: Vec<Connection>,
+        extinction: Option<Extinction>,
     ) -> Island {
         Island {
             habitat,
             config,
             disaster,
             connections,
+            immigrations: Vec::new(),
+            extinction,
+            seeds: Vec::new(),
+            genebank: None,
+            ticks: Ticks(0),
+            history: History::default(),
+            id: 0,
+            retired: false,
+            control: IslandControl::default(),
+            metrics: IslandMetrics::default(),
+            events: Vec::new(),
+            observers: Observers::default(),
         }
     }
 
-    pub fn update(&mut self, ticks: Ticks, rng: &mut SmallRng) {
-        self.habitat.update(rng, &self.config);
+    pub fn config(&self) -> &HabitatConfig {
+        &self.config
+    }
+
+    pub fn settings(&self) -> IslandSettings {
+        IslandSettings {
+            config: self.config.clone(),
+            disaster: self.disaster.clone(),
+            connections: self.connections.clone(),
+            extinction: self.extinction.clone(),
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: IslandSettings) {
+        self.config = settings.config;
+        self.disaster = settings.disaster;
+        self.connections = settings.connections;
+        self.extinction = settings.extinction;
+    }
+
+    pub fn update_genebank(&mut self) {
+        if let Some(genotype) = self.habitat.dominant_genotype() {
+            self.genebank = Some(genotype);
+        }
+    }
+
+    // a computer to reseed with from the island itself; neighbors are
+    // handled by the world
+    pub fn reseed_computer(&self, rng: &mut SmallRng) -> Option<Computer> {
+        let extinction = self.extinction.as_ref()?;
+        match extinction.policy {
+            ExtinctionPolicy::Seed => self.seeds.choose(rng).cloned(),
+            ExtinctionPolicy::Genebank => self.genebank.as_ref().map(|genotype| {
+                let mut computer = Computer::new(0, extinction.resources);
+                computer.memory = Memory::from_values(genotype.clone());
+                computer.add_processor(0);
+                computer
+            }),
+            ExtinctionPolicy::Neighbor => None,
+        }
+    }
+
+    pub fn update(&mut self, rng: &mut SmallRng) {
+        let ticks = self.ticks;
+        self.metrics.instructions += self.habitat.update(rng, &self.config);
 
         let mutate = ticks.is_at(self.config.mutation_frequency);
         if mutate {
@@ -61,5 +356,181 @@ impl Island {
                 self.habitat.wipeout(rng, disaster.width, disaster.height);
             }
         }
+        for immigration in &self.immigrations {
+            if ticks.is_at(immigration.frequency) {
+                immigration
+                    .pattern
+                    .place(&mut self.habitat, &immigration.computer, rng);
+            }
+        }
+        self.collect_events(ticks);
+        self.ticks = ticks.tick();
+        self.history.update(self.ticks, &self.habitat);
+    }
+
+    fn collect_events(&mut self, ticks: Ticks) {
+        let island_id = self.id;
+        self.events
+            .extend(self.habitat.take_events().into_iter().map(|kind| Event {
+                island_id,
+                ticks,
+                kind,
+            }));
+    }
+
+    pub fn take_events(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.events)
+    }
+
+    // the events of computers placed between updates, such as migrants and
+    // seeds after an extinction
+    pub fn take_placed_events(&mut self) -> Vec<Event> {
+        self.collect_events(self.ticks);
+        self.take_events()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::habitat_config;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_scatter_in_rect() {
+        let mut habitat = Habitat::new(10, 10, 5);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        let pattern = SeedPattern::Scatter {
+            amount: 4,
+            rect: Some(Rectangle::new(2, 2, 2, 2)),
+        };
+        let placed = pattern.place(&mut habitat, &Computer::new(1, 1), &mut rng);
+        assert_eq!(placed, 4);
+        assert_eq!(habitat.computers_amount(), 4);
+        for coords in [(2, 2), (3, 2), (2, 3), (3, 3)] {
+            assert!(!habitat.is_empty(coords));
+        }
+    }
+
+    #[test]
+    fn test_scatter_full_rect() {
+        let mut habitat = Habitat::new(10, 10, 5);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        let pattern = SeedPattern::Scatter {
+            amount: 5,
+            rect: Some(Rectangle::new(0, 0, 1, 1)),
+        };
+        let placed = pattern.place(&mut habitat, &Computer::new(1, 1), &mut rng);
+        assert_eq!(placed, 1);
+    }
+
+    #[test]
+    fn test_grid() {
+        let mut habitat = Habitat::new(10, 10, 5);
+        habitat.set((2, 7), Computer::new(1, 1));
+        let mut rng = SmallRng::from_seed([0; 32]);
+        let pattern = SeedPattern::Grid {
+            columns: 2,
+            rows: 2,
+            rect: None,
+        };
+        let placed = pattern.place(&mut habitat, &Computer::new(1, 1), &mut rng);
+        // (2, 7) was already taken
+        assert_eq!(placed, 3);
+        for coords in [(2, 2), (7, 2), (7, 7)] {
+            assert!(!habitat.is_empty(coords));
+        }
+    }
+
+    #[test]
+    fn test_migration_filter() {
+        let mut computer = Computer::new(10, 5);
+        computer.add_processor(0);
+        let filter: MigrationFilter =
+            serde_json::from_str(r#"{ "min_memory_size": 5, "max_processors": 1 }"#).unwrap();
+        assert!(filter.accepts(&computer));
+        computer.add_processor(1);
+        assert!(!filter.accepts(&computer));
+
+        let computer = Computer::new(10, 5);
+        let filter = MigrationFilter {
+            deny: vec![computer.genotype()],
+            ..Default::default()
+        };
+        assert!(!filter.accepts(&computer));
+        let filter = MigrationFilter {
+            allow: Some(vec![computer.genotype()]),
+            ..Default::default()
+        };
+        assert!(filter.accepts(&computer));
+        assert!(!filter.accepts(&Computer::new(11, 5)));
+    }
+
+    #[test]
+    fn test_island_control_step() {
+        let mut control = IslandControl::default();
+        assert!(control.take_tick());
+        control.paused = true;
+        control.steps = 2;
+        assert!(control.take_tick());
+        assert!(control.take_tick());
+        assert!(!control.take_tick());
+        assert!(control.paused);
+    }
+
+    #[test]
+    fn test_genotype_round_trip() {
+        let genotype = Computer::new(10, 5).genotype();
+        let json = serde_json::to_string(&genotype).unwrap();
+        assert_eq!(serde_json::from_str::<Genotype>(&json).unwrap(), genotype);
+        assert!(serde_json::from_str::<Genotype>(r#""xyz""#).is_err());
+    }
+
+    fn extinction_island(policy: ExtinctionPolicy) -> Island {
+        Island::new(
+            Habitat::new(5, 5, 5),
+            habitat_config(),
+            None,
+            Vec::new(),
+            Some(Extinction {
+                policy,
+                check_frequency: Duration::from_secs(1),
+                pattern: SeedPattern::Scatter {
+                    amount: 1,
+                    rect: None,
+                },
+                resources: 7,
+            }),
+        )
+    }
+
+    #[test]
+    fn test_reseed_from_seed() {
+        let mut island = extinction_island(ExtinctionPolicy::Seed);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        assert!(island.reseed_computer(&mut rng).is_none());
+        island.seeds.push(Computer::new(3, 1));
+        let computer = island.reseed_computer(&mut rng).unwrap();
+        assert_eq!(computer.memory.values.len(), 3);
+    }
+
+    #[test]
+    fn test_reseed_from_genebank() {
+        let mut island = extinction_island(ExtinctionPolicy::Genebank);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        assert!(island.reseed_computer(&mut rng).is_none());
+
+        let mut computer = Computer::new(2, 1);
+        computer.memory.values = vec![1, 2];
+        island.habitat.set((0, 0), computer);
+        island.update_genebank();
+        island.habitat.die((0, 0));
+        // an empty habitat doesn't clear the genebank
+        island.update_genebank();
+
+        let computer = island.reseed_computer(&mut rng).unwrap();
+        assert_eq!(computer.memory.values, [1, 2]);
+        assert_eq!(computer.resources, 7);
+        assert_eq!(computer.processors.len(), 1);
     }
 }
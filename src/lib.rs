@@ -0,0 +1,49 @@
+extern crate num;
+#[macro_use]
+extern crate num_derive;
+extern crate serde_big_array;
+
+pub mod assembler;
+pub mod builder;
+mod cli;
+mod client_command;
+mod command;
+pub mod computer;
+pub mod config;
+pub mod direction;
+pub mod events;
+mod fill;
+pub mod follow;
+pub mod frames;
+pub mod generate;
+pub mod habitat;
+pub mod history;
+mod image;
+pub mod info;
+pub mod inspect;
+pub mod instruction;
+pub mod island;
+pub mod memory;
+mod message;
+pub mod metrics;
+pub mod migration;
+pub mod observer;
+pub mod overrides;
+pub mod processor;
+pub mod rectangle;
+mod serve;
+pub mod ticks;
+pub mod topology;
+mod tui;
+pub mod view;
+pub mod want;
+pub mod world;
+
+#[cfg(test)]
+mod testutil;
+
+pub use builder::WorldBuilder;
+pub use client_command::{IslandCommand, TopologyEdit};
+pub use command::run_cli;
+pub use fill::{FillScheme, Rgb};
+pub use world::World;
@@ -3,16 +3,22 @@ use serde_derive::{Deserialize, Serialize};
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Memory {
     pub values: Vec<u8>,
+    // only kept during an update of a computer that's being followed
+    #[serde(skip)]
+    writes: Option<Vec<(usize, u8)>>,
 }
 
 impl Memory {
     pub fn new(size: usize) -> Memory {
         let values: Vec<u8> = vec![u8::MAX; size];
-        Memory { values }
+        Memory::from_values(values)
     }
 
     pub fn from_values(values: Vec<u8>) -> Memory {
-        Memory { values }
+        Memory {
+            values,
+            writes: None,
+        }
     }
 
     pub fn read(&self, index: usize) -> Option<u8> {
@@ -27,8 +33,20 @@ impl Memory {
             return false;
         }
         self.values[index] = value;
+        if let Some(writes) = &mut self.writes {
+            writes.push((index, value));
+        }
         true
     }
+
+    pub fn record_writes(&mut self) {
+        self.writes = Some(Vec::new());
+    }
+
+    // the writes since record_writes, which also stops recording
+    pub fn take_writes(&mut self) -> Vec<(usize, u8)> {
+        self.writes.take().unwrap_or_default()
+    }
 }
 
 #[cfg(test)]
@@ -48,6 +66,18 @@ mod tests {
         assert!(!memory.write(100, 10));
     }
 
+    #[test]
+    fn test_record_writes() {
+        let mut memory = Memory::new(10);
+        memory.write(0, 1);
+        memory.record_writes();
+        memory.write(1, 2);
+        memory.write(100, 3);
+        assert_eq!(memory.take_writes(), vec![(1, 2)]);
+        memory.write(2, 4);
+        assert!(memory.take_writes().is_empty());
+    }
+
     #[test]
     fn test_read_in_bounds() {
         let mut memory = Memory::new(10);
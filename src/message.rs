@@ -0,0 +1,48 @@
+use std::sync::Mutex;
+
+// messages for whoever runs apilar, like islands going extinct. they're
+// printed, except while the terminal UI runs: it would be drawn over, so the
+// UI shows them itself
+
+static CAPTURED: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+macro_rules! message {
+    ($($arg:tt)*) => {
+        $crate::message::add(format!($($arg)*))
+    };
+}
+
+pub(crate) use message;
+
+pub fn add(text: String) {
+    match &mut *CAPTURED.lock().unwrap() {
+        Some(captured) => captured.push(text),
+        None => println!("{}", text),
+    }
+}
+
+// messages are kept for take until this is dropped
+pub struct Capture;
+
+impl Capture {
+    pub fn start() -> Capture {
+        *CAPTURED.lock().unwrap() = Some(Vec::new());
+        Capture
+    }
+
+    // the messages since the last take
+    pub fn take(&self) -> Vec<String> {
+        CAPTURED
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        *CAPTURED.lock().unwrap() = None;
+    }
+}
@@ -0,0 +1,219 @@
+use crate::world::WorldState;
+use std::fmt::Write;
+use std::time::UNIX_EPOCH;
+
+// metrics in the Prometheus text exposition format
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    Gauge,
+    Counter,
+}
+
+impl Metrics {
+    // a metric family with samples labeled by name and value
+    pub fn add(
+        &mut self,
+        name: &str,
+        help: &str,
+        kind: Kind,
+        samples: &[(Vec<(&str, String)>, f64)],
+    ) {
+        let kind = match kind {
+            Kind::Gauge => "gauge",
+            Kind::Counter => "counter",
+        };
+        // writing to a string can't fail
+        writeln!(self.text, "# HELP {} {}", name, help).unwrap();
+        writeln!(self.text, "# TYPE {} {}", name, kind).unwrap();
+        for (labels, value) in samples {
+            if labels.is_empty() {
+                writeln!(self.text, "{} {}", name, value).unwrap();
+            } else {
+                let labels: Vec<String> = labels
+                    .iter()
+                    .map(|(label, value)| format!("{}=\"{}\"", label, value))
+                    .collect();
+                writeln!(self.text, "{}{{{}}} {}", name, labels.join(","), value).unwrap();
+            }
+        }
+    }
+
+    pub fn single(&mut self, name: &str, help: &str, kind: Kind, value: f64) {
+        self.add(name, help, kind, &[(Vec::new(), value)]);
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl From<&WorldState> for Metrics {
+    fn from(world_state: &WorldState) -> Metrics {
+        let mut metrics = Metrics::default();
+        let mut computers = Vec::new();
+        let mut processors = Vec::new();
+        let mut free_resources = Vec::new();
+        let mut bound_resources = Vec::new();
+        let mut memory_resources = Vec::new();
+        let mut ticks = Vec::new();
+        let mut ticks_per_second = Vec::new();
+        let mut instructions_per_second = Vec::new();
+        let mut mutex_wait = Vec::new();
+        for (island_id, island) in world_state.get_islands().iter().enumerate() {
+            let island = island.lock().unwrap();
+            let labels = vec![("island", island_id.to_string())];
+            let (free, bound, memory) = island.habitat.resources_amounts();
+            computers.push((labels.clone(), island.habitat.computers_amount() as f64));
+            processors.push((labels.clone(), island.habitat.processors_amount() as f64));
+            free_resources.push((labels.clone(), free as f64));
+            bound_resources.push((labels.clone(), bound as f64));
+            memory_resources.push((labels.clone(), memory as f64));
+            ticks.push((labels.clone(), island.ticks.0 as f64));
+            ticks_per_second.push((labels.clone(), island.metrics.ticks_per_second));
+            instructions_per_second.push((labels.clone(), island.metrics.instructions_per_second));
+            mutex_wait.push((labels, island.metrics.mutex_wait));
+        }
+        metrics.add(
+            "apilar_island_computers",
+            "Computers on the island",
+            Kind::Gauge,
+            &computers,
+        );
+        metrics.add(
+            "apilar_island_processors",
+            "Processors on the island",
+            Kind::Gauge,
+            &processors,
+        );
+        metrics.add(
+            "apilar_island_free_resources",
+            "Resources not bound to computers",
+            Kind::Gauge,
+            &free_resources,
+        );
+        metrics.add(
+            "apilar_island_bound_resources",
+            "Resources bound to computers",
+            Kind::Gauge,
+            &bound_resources,
+        );
+        metrics.add(
+            "apilar_island_memory_resources",
+            "Resources in computer memory",
+            Kind::Gauge,
+            &memory_resources,
+        );
+        metrics.add(
+            "apilar_island_ticks_total",
+            "Ticks the island has run",
+            Kind::Counter,
+            &ticks,
+        );
+        metrics.add(
+            "apilar_island_ticks_per_second",
+            "Ticks the island runs per second",
+            Kind::Gauge,
+            &ticks_per_second,
+        );
+        metrics.add(
+            "apilar_island_instructions_per_second",
+            "Instructions the island executes per second",
+            Kind::Gauge,
+            &instructions_per_second,
+        );
+        metrics.add(
+            "apilar_island_mutex_wait_ratio",
+            "Fraction of time the island waits for its lock",
+            Kind::Gauge,
+            &mutex_wait,
+        );
+
+        let migrations: Vec<(Vec<(&str, String)>, f64)> = world_state
+            .get_migrations()
+            .flows()
+            .iter()
+            .map(|flow| {
+                (
+                    vec![
+                        ("from_island", flow.from_island_id.to_string()),
+                        ("to_island", flow.to_island_id.to_string()),
+                    ],
+                    flow.migrants as f64,
+                )
+            })
+            .collect();
+        metrics.add(
+            "apilar_migrations_total",
+            "Computers that migrated between islands",
+            Kind::Counter,
+            &migrations,
+        );
+
+        let autosave = world_state.get_autosave();
+        metrics.single(
+            "apilar_autosave_enabled",
+            "Whether the world is saved regularly",
+            Kind::Gauge,
+            autosave.enabled as u8 as f64,
+        );
+        metrics.single(
+            "apilar_autosaves_total",
+            "Dumps written by autosave",
+            Kind::Counter,
+            autosave.saves as f64,
+        );
+        metrics.single(
+            "apilar_autosave_failed",
+            "Whether autosave stopped after failing to write",
+            Kind::Gauge,
+            autosave.failed as u8 as f64,
+        );
+        let last_save = autosave
+            .last_save
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0.0, |duration| duration.as_secs_f64());
+        metrics.single(
+            "apilar_autosave_last_timestamp_seconds",
+            "When the last dump was written",
+            Kind::Gauge,
+            last_save,
+        );
+        metrics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add() {
+        let mut metrics = Metrics::default();
+        metrics.add(
+            "apilar_island_computers",
+            "Computers on the island",
+            Kind::Gauge,
+            &[
+                (vec![("island", "0".to_string())], 3.0),
+                (vec![("island", "1".to_string())], 0.5),
+            ],
+        );
+        metrics.single("apilar_autosaves_total", "Dumps", Kind::Counter, 2.0);
+        assert_eq!(
+            metrics.text(),
+            "# HELP apilar_island_computers Computers on the island\n\
+             # TYPE apilar_island_computers gauge\n\
+             apilar_island_computers{island=\"0\"} 3\n\
+             apilar_island_computers{island=\"1\"} 0.5\n\
+             # HELP apilar_autosaves_total Dumps\n\
+             # TYPE apilar_autosaves_total counter\n\
+             apilar_autosaves_total 2\n"
+        );
+    }
+}
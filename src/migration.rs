@@ -0,0 +1,232 @@
+use crate::computer::Genotype;
+use crate::habitat::Coords;
+use crate::message::message;
+use crate::ticks::Ticks;
+use anyhow::Result;
+use rustc_hash::FxHashMap;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::File;
+use std::io::{LineWriter, Write};
+
+// how many recent migrations we remember
+const RECENT_EVENTS: usize = 10000;
+// how many genotypes we count per connection. migrants are often mutated,
+// so there are many genotypes that are seen only once; when there are twice
+// as many, the least common are dropped and only counted as other genotypes
+const FLOW_GENOTYPES: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationEvent {
+    // ticks of the source island
+    pub ticks: Ticks,
+    pub from_island_id: usize,
+    // of the connection on the source island
+    #[serde(default)]
+    pub connection_id: usize,
+    pub from_coords: Coords,
+    pub to_island_id: usize,
+    pub to_coords: Coords,
+    // before any mutation in transit, as seen by the connection's filter
+    pub genotype: Genotype,
+}
+
+// what flowed along a connection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Flow {
+    migrants: u64,
+    genotypes: FxHashMap<Genotype, u64>,
+    // migrants whose genotypes were dropped from the counts
+    other: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MigrationLog {
+    events: VecDeque<MigrationEvent>,
+    // keyed by (from island, connection, to island)
+    #[serde(default)]
+    connection_flows: BTreeMap<(usize, usize, usize), Flow>,
+    // every event is also written here, if we have it
+    #[serde(skip)]
+    writer: Option<LineWriter<File>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowInfo {
+    pub from_island_id: usize,
+    pub connection_id: usize,
+    pub to_island_id: usize,
+    pub migrants: u64,
+    // most common first
+    pub genotypes: Vec<GenotypeCount>,
+    // migrants of less common genotypes
+    pub other: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GenotypeCount {
+    pub genotype: Genotype,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationsInfo {
+    pub flows: Vec<FlowInfo>,
+    pub events: Vec<MigrationEvent>,
+}
+
+impl MigrationLog {
+    pub fn open(&mut self, filename: &str) -> Result<()> {
+        self.writer = Some(LineWriter::new(File::create(filename)?));
+        Ok(())
+    }
+
+    pub fn record(&mut self, event: MigrationEvent) {
+        if let Some(writer) = &mut self.writer {
+            let written = serde_json::to_string(&event)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Ok(writeln!(writer, "{}", json)?));
+            if let Err(error) = written {
+                message!("Could not write migration log: {}", error);
+                self.writer = None;
+            }
+        }
+        self.connection_flows
+            .entry((
+                event.from_island_id,
+                event.connection_id,
+                event.to_island_id,
+            ))
+            .or_default()
+            .record(event.genotype);
+        if self.events.len() == RECENT_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    pub fn flows(&self) -> Vec<FlowInfo> {
+        self.connection_flows
+            .iter()
+            .map(
+                |(&(from_island_id, connection_id, to_island_id), flow)| FlowInfo {
+                    from_island_id,
+                    connection_id,
+                    to_island_id,
+                    migrants: flow.migrants,
+                    genotypes: flow.most_common(),
+                    other: flow.other,
+                },
+            )
+            .collect()
+    }
+
+    // the most recent events, oldest first
+    pub fn recent_events(&self, limit: usize) -> Vec<MigrationEvent> {
+        let skip = self.events.len().saturating_sub(limit);
+        self.events.iter().skip(skip).cloned().collect()
+    }
+
+    pub fn info(&self, limit: usize) -> MigrationsInfo {
+        MigrationsInfo {
+            flows: self.flows(),
+            events: self.recent_events(limit),
+        }
+    }
+}
+
+impl Flow {
+    fn record(&mut self, genotype: Genotype) {
+        self.migrants += 1;
+        *self.genotypes.entry(genotype).or_insert(0) += 1;
+        if self.genotypes.len() >= 2 * FLOW_GENOTYPES {
+            let dropped: Vec<GenotypeCount> = self.most_common().split_off(FLOW_GENOTYPES);
+            for genotype_count in dropped {
+                self.genotypes.remove(&genotype_count.genotype);
+                self.other += genotype_count.count;
+            }
+        }
+    }
+
+    fn most_common(&self) -> Vec<GenotypeCount> {
+        let mut genotypes: Vec<GenotypeCount> = self
+            .genotypes
+            .iter()
+            .map(|(&genotype, &count)| GenotypeCount { genotype, count })
+            .collect();
+        genotypes.sort_by(|a, b| b.count.cmp(&a.count).then(a.genotype.cmp(&b.genotype)));
+        genotypes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(from_island_id: usize, to_island_id: usize, genotype: u64) -> MigrationEvent {
+        MigrationEvent {
+            ticks: Ticks(0),
+            from_island_id,
+            connection_id: 0,
+            from_coords: (0, 0),
+            to_island_id,
+            to_coords: (1, 1),
+            genotype: Genotype(genotype),
+        }
+    }
+
+    #[test]
+    fn test_flows() {
+        let mut log = MigrationLog::default();
+        log.record(event(0, 1, 5));
+        log.record(event(0, 1, 7));
+        log.record(event(0, 1, 7));
+        log.record(event(1, 0, 5));
+        log.record(MigrationEvent {
+            connection_id: 1,
+            ..event(0, 1, 5)
+        });
+        let flows = log.flows();
+        assert_eq!(flows.len(), 3);
+        assert_eq!((flows[0].from_island_id, flows[0].to_island_id), (0, 1));
+        assert_eq!(flows[0].migrants, 3);
+        assert_eq!(flows[0].genotypes[0].genotype, Genotype(7));
+        assert_eq!(flows[0].genotypes[0].count, 2);
+        assert_eq!(flows[1].connection_id, 1);
+        assert_eq!(flows[1].migrants, 1);
+        assert_eq!(flows[2].from_island_id, 1);
+    }
+
+    #[test]
+    fn test_flow_genotypes_capped() {
+        let mut log = MigrationLog::default();
+        for _ in 0..3 {
+            log.record(event(0, 1, 0));
+        }
+        for genotype in 1..(3 * FLOW_GENOTYPES as u64) {
+            log.record(event(0, 1, genotype));
+        }
+        let flow = &log.flows()[0];
+        assert_eq!(flow.migrants, 3 * FLOW_GENOTYPES as u64 + 2);
+        assert!(flow.genotypes.len() < 2 * FLOW_GENOTYPES);
+        assert_eq!(flow.genotypes[0].genotype, Genotype(0));
+        assert_eq!(flow.genotypes[0].count, 3);
+        let counted: u64 = flow.genotypes.iter().map(|g| g.count).sum();
+        assert_eq!(counted + flow.other, flow.migrants);
+    }
+
+    #[test]
+    fn test_recent_events() {
+        let mut log = MigrationLog::default();
+        for i in 0..(RECENT_EVENTS + 5) {
+            log.record(event(0, 1, i as u64));
+        }
+        assert_eq!(log.events.len(), RECENT_EVENTS);
+        let recent = log.recent_events(2);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[1].genotype, Genotype((RECENT_EVENTS + 4) as u64));
+        assert_eq!(log.flows()[0].migrants, (RECENT_EVENTS + 5) as u64);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::events::{Event, EventKind};
+use crate::island::Island;
+use crate::ticks::Ticks;
+
+// custom analysis that runs inside the simulation. every island has
+// observers of its own, made for it by the observer factories of the world.
+// the hooks are called on the thread of the island, right after it updates
+// and while it's still locked, so they see exactly what happened. keep them
+// quick, as the island waits for them; hand slow work to another thread
+pub trait IslandObserver: Send {
+    // after every update of the island
+    fn on_update(&mut self, _island: &Island) {}
+
+    fn on_birth(&mut self, _island: &Island, _event: &Event) {}
+
+    fn on_death(&mut self, _island: &Island, _event: &Event) {}
+
+    fn on_mutation(&mut self, _island: &Island, _event: &Event) {}
+
+    // any event, including those above and the migrations from the island
+    fn on_event(&mut self, _island: &Island, _event: &Event) {}
+
+    // how many ticks between calls of on_ticks, if at all
+    fn ticks_frequency(&self) -> Option<Ticks> {
+        None
+    }
+
+    fn on_ticks(&mut self, _island: &Island) {}
+}
+
+// makes the observers of an island, both for the islands there are and for
+// those added or cloned later
+pub type ObserverFactory = Box<dyn Fn(&Island) -> Box<dyn IslandObserver> + Send + Sync>;
+
+#[derive(Default)]
+pub struct ObserverFactories {
+    factories: Vec<ObserverFactory>,
+}
+
+impl std::fmt::Debug for ObserverFactories {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ObserverFactories")
+            .field("factories", &self.factories.len())
+            .finish()
+    }
+}
+
+impl ObserverFactories {
+    pub fn add(&mut self, factory: ObserverFactory) {
+        self.factories.push(factory);
+    }
+
+    pub fn observe(&self, island: &mut Island) {
+        for factory in &self.factories {
+            let observer = factory(island);
+            island.observers.add(observer);
+        }
+    }
+}
+
+// the observers of a single island, so islands never wait for each other
+#[derive(Default)]
+pub struct Observers {
+    observers: Vec<Box<dyn IslandObserver>>,
+}
+
+impl std::fmt::Debug for Observers {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Observers")
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
+
+// a cloned island gets observers of its own from the factories
+impl Clone for Observers {
+    fn clone(&self) -> Observers {
+        Observers::default()
+    }
+}
+
+impl Observers {
+    pub fn is_active(&self) -> bool {
+        !self.observers.is_empty()
+    }
+
+    pub fn add(&mut self, observer: Box<dyn IslandObserver>) {
+        self.observers.push(observer);
+    }
+
+    // the island has just updated, producing these events
+    pub fn updated(&mut self, island: &Island, events: &[Event]) {
+        self.events(island, events);
+        for observer in self.observers.iter_mut() {
+            observer.on_update(island);
+            if let Some(frequency) = observer.ticks_frequency() {
+                // island ticks count the updates done, so this is after
+                // every so many of them
+                if frequency.0 > 0 && island.ticks.is_at(frequency) {
+                    observer.on_ticks(island);
+                }
+            }
+        }
+    }
+
+    // events that happened outside of an update, like migrations
+    pub fn events(&mut self, island: &Island, events: &[Event]) {
+        for observer in self.observers.iter_mut() {
+            for event in events {
+                match event.kind {
+                    EventKind::Birth { .. } => observer.on_birth(island, event),
+                    EventKind::Death { .. } => observer.on_death(island, event),
+                    EventKind::Mutation { .. } => observer.on_mutation(island, event),
+                    _ => {}
+                }
+                observer.on_event(island, event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::DeathCause;
+    use crate::habitat::Habitat;
+    use crate::testutil::habitat_config;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Counts {
+        updates: u64,
+        deaths: u64,
+        ticks: Vec<u64>,
+    }
+
+    struct Counter(Arc<Mutex<Counts>>);
+
+    impl IslandObserver for Counter {
+        fn on_update(&mut self, _island: &Island) {
+            self.0.lock().unwrap().updates += 1;
+        }
+
+        fn on_death(&mut self, _island: &Island, _event: &Event) {
+            self.0.lock().unwrap().deaths += 1;
+        }
+
+        fn ticks_frequency(&self) -> Option<Ticks> {
+            Some(Ticks(2))
+        }
+
+        fn on_ticks(&mut self, island: &Island) {
+            self.0.lock().unwrap().ticks.push(island.ticks.0);
+        }
+    }
+
+    #[test]
+    fn test_updated() {
+        let counts = Arc::new(Mutex::new(Counts::default()));
+        let mut factories = ObserverFactories::default();
+        let factory_counts = Arc::clone(&counts);
+        factories.add(Box::new(move |_island: &Island| {
+            Box::new(Counter(Arc::clone(&factory_counts))) as Box<dyn IslandObserver>
+        }));
+
+        let mut island = Island::new(
+            Habitat::new(5, 5, 5),
+            habitat_config(),
+            None,
+            Vec::new(),
+            None,
+        );
+        assert!(!island.observers.is_active());
+        factories.observe(&mut island);
+        assert!(island.observers.is_active());
+        // a clone doesn't share the observers of the original
+        assert!(!island.clone().observers.is_active());
+        let mut observers = std::mem::take(&mut island.observers);
+        let death = Event {
+            island_id: 0,
+            ticks: Ticks(0),
+            kind: EventKind::Death {
+                coords: (0, 0),
+                computer_id: 1,
+                cause: DeathCause::Random,
+            },
+        };
+        for ticks in 1..=4 {
+            island.ticks = Ticks(ticks);
+            observers.updated(&island, std::slice::from_ref(&death));
+        }
+        let counts = counts.lock().unwrap();
+        assert_eq!(counts.updates, 4);
+        assert_eq!(counts.deaths, 4);
+        assert_eq!(counts.ticks, vec![2, 4]);
+    }
+}
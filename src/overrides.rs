@@ -0,0 +1,204 @@
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+// a parameter override given on the command line, such as
+// `islands[*].config.mutation_frequency=5000`
+
+#[derive(Debug, Clone)]
+pub struct Override {
+    text: String,
+    path: Vec<Segment>,
+    value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    key: String,
+    selector: Option<Selector>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    All,
+    Indices(Vec<usize>),
+}
+
+impl Override {
+    pub fn parse(s: &str) -> Result<Override, String> {
+        let (path, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("`{}` should look like path=value", s))?;
+        let path = path
+            .split('.')
+            .map(Segment::parse)
+            .collect::<Result<Vec<Segment>, String>>()?;
+        // anything that isn't valid JSON is taken to be a string
+        let value =
+            serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+        Ok(Override {
+            text: s.to_string(),
+            path,
+            value,
+        })
+    }
+
+    pub fn apply(&self, target: &mut Value) -> Result<()> {
+        apply_at(target, &self.path, &self.value)
+            .map_err(|error| anyhow!("Cannot apply `{}`: {}", self.text, error))
+    }
+}
+
+impl Segment {
+    fn parse(s: &str) -> Result<Segment, String> {
+        let (key, selector) = match s.split_once('[') {
+            Some((key, rest)) => {
+                let selector = rest
+                    .strip_suffix(']')
+                    .ok_or_else(|| format!("missing `]` in `{}`", s))?;
+                (key, Some(Selector::parse(selector)?))
+            }
+            None => (s, None),
+        };
+        if key.is_empty() {
+            return Err(format!("empty field name in `{}`", s));
+        }
+        Ok(Segment {
+            key: key.to_string(),
+            selector,
+        })
+    }
+}
+
+impl Selector {
+    fn parse(s: &str) -> Result<Selector, String> {
+        if s == "*" {
+            return Ok(Selector::All);
+        }
+        let indices = s
+            .split(',')
+            .map(|index| {
+                index
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("`{}` isn't a valid index", index))
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+        Ok(Selector::Indices(indices))
+    }
+}
+
+fn apply_at(target: &mut Value, path: &[Segment], value: &Value) -> Result<()> {
+    let (segment, rest) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            *target = value.clone();
+            return Ok(());
+        }
+    };
+    let child = target
+        .as_object_mut()
+        .and_then(|object| object.get_mut(&segment.key))
+        .ok_or_else(|| anyhow!("unknown field `{}`", segment.key))?;
+    match &segment.selector {
+        None => apply_at(child, rest, value),
+        Some(selector) => {
+            let items = child
+                .as_array_mut()
+                .ok_or_else(|| anyhow!("`{}` is not a list", segment.key))?;
+            let indices = match selector {
+                Selector::All => (0..items.len()).collect(),
+                Selector::Indices(indices) => indices.clone(),
+            };
+            for index in indices {
+                let item = items
+                    .get_mut(index)
+                    .ok_or_else(|| anyhow!("index {} out of range for `{}`", index, segment.key))?;
+                apply_at(item, rest, value)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// apply overrides to anything that can round-trip through JSON
+pub fn apply_overrides<T: Serialize + DeserializeOwned>(
+    target: &T,
+    overrides: &[Override],
+) -> Result<T> {
+    let mut value = serde_json::to_value(target)?;
+    for item in overrides {
+        item.apply(&mut value)?;
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let item = Override::parse("islands[*].config.mutation_frequency=5000").unwrap();
+        assert_eq!(item.value, json!(5000));
+        assert_eq!(item.path.len(), 3);
+        assert_eq!(item.path[0].selector, Some(Selector::All));
+
+        let item = Override::parse("islands[0, 2].name=foo").unwrap();
+        assert_eq!(item.path[0].selector, Some(Selector::Indices(vec![0, 2])));
+        assert_eq!(item.value, json!("foo"));
+    }
+
+    #[test]
+    fn test_parse_failure() {
+        assert!(Override::parse("islands[*].width").is_err());
+        assert!(Override::parse("islands[x].width=1").is_err());
+        assert!(Override::parse("islands[0.width=1").is_err());
+        assert!(Override::parse("islands..width=1").is_err());
+    }
+
+    #[test]
+    fn test_apply_all() {
+        let mut value = json!({ "islands": [{ "width": 1 }, { "width": 2 }] });
+        Override::parse("islands[*].width=5")
+            .unwrap()
+            .apply(&mut value)
+            .unwrap();
+        assert_eq!(
+            value,
+            json!({ "islands": [{ "width": 5 }, { "width": 5 }] })
+        );
+    }
+
+    #[test]
+    fn test_apply_selected() {
+        let mut value = json!({ "islands": [{ "width": 1 }, { "width": 2 }, { "width": 3 }] });
+        Override::parse("islands[0,2].width=5")
+            .unwrap()
+            .apply(&mut value)
+            .unwrap();
+        assert_eq!(
+            value,
+            json!({ "islands": [{ "width": 5 }, { "width": 2 }, { "width": 5 }] })
+        );
+    }
+
+    #[test]
+    fn test_apply_unknown_field() {
+        let mut value = json!({ "islands": [{ "width": 1 }] });
+        assert!(Override::parse("islands[*].depth=5")
+            .unwrap()
+            .apply(&mut value)
+            .is_err());
+        assert!(Override::parse("islands[1].width=5")
+            .unwrap()
+            .apply(&mut value)
+            .is_err());
+        assert!(Override::parse("islands.width=5")
+            .unwrap()
+            .apply(&mut value)
+            .is_err());
+    }
+}
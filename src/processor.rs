@@ -46,13 +46,13 @@ impl Processor {
         wants: &mut Wants,
         rng: &mut SmallRng,
         metabolism: &Metabolism,
-    ) {
+    ) -> bool {
         if !self.alive {
-            return;
+            return false;
         }
         if self.ip >= memory.values.len() {
             self.alive = false;
-            return;
+            return false;
         }
         let value = memory.values[self.ip];
         if let Some(instruction) = Instruction::decode(value) {
@@ -63,8 +63,11 @@ impl Processor {
         } else {
             self.jumped = false;
         }
+        true
     }
 
+    // returns how many were executed, fewer than amount if the processor
+    // ends
     pub fn execute_amount(
         &mut self,
         memory: &mut Memory,
@@ -72,10 +75,15 @@ impl Processor {
         rng: &mut SmallRng,
         amount: usize,
         metabolism: &Metabolism,
-    ) {
+    ) -> u64 {
+        let mut executed = 0;
         for _ in 0..amount {
-            self.execute(memory, wants, rng, metabolism);
+            if !self.execute(memory, wants, rng, metabolism) {
+                break;
+            }
+            executed += 1;
         }
+        executed
     }
 
     pub fn end(&mut self) {
@@ -151,11 +159,7 @@ impl Processor {
     }
 
     fn address_distance(&self, address: usize) -> usize {
-        if address > self.ip {
-            address - self.ip
-        } else {
-            self.ip - address
-        }
+        address.abs_diff(self.ip)
     }
 
     pub fn address(&self) -> u64 {
@@ -254,11 +258,7 @@ impl Processor {
         if result >= memory.values.len() {
             return None;
         }
-        let distance = if result > self.ip {
-            result - self.ip
-        } else {
-            self.ip - result
-        };
+        let distance = result.abs_diff(self.ip);
         if distance > MAX_ADDRESS_DISTANCE {
             return None;
         }
@@ -321,6 +321,27 @@ fn adjust_backward(address: usize, start: usize, distance: usize) -> Option<usiz
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_execute_amount_past_end() {
+        let mut memory = Memory::new(3);
+        let mut processor = Processor::new(0);
+        let executed = processor.execute_amount(
+            &mut memory,
+            &mut Wants::new(),
+            &mut SmallRng::from_seed([0; 32]),
+            5,
+            &Metabolism {
+                max_eat_amount: 0,
+                max_grow_amount: 0,
+                max_shrink_amount: 0,
+            },
+        );
+        // it runs off the end of memory, which ends it
+        assert_eq!(executed, 3);
+        assert!(!processor.alive);
+    }
 
     #[test]
     fn test_compact_stack() {
@@ -1,7 +1,8 @@
 use rand::Rng;
 use serde_derive::{Deserialize, Serialize};
+use std::ops::Range;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Rectangle {
     x: usize,
     y: usize,
@@ -10,9 +11,55 @@ pub struct Rectangle {
 }
 
 impl Rectangle {
+    pub fn new(x: usize, y: usize, w: usize, h: usize) -> Rectangle {
+        Rectangle { x, y, w, h }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.w == 0 || self.h == 0
+    }
+
+    // does this rectangle fit entirely in a habitat of this size?
+    pub fn fits(&self, width: usize, height: usize) -> bool {
+        self.x + self.w <= width && self.y + self.h <= height
+    }
+
+    // the part of this rectangle that lies in a habitat of this size
+    pub fn clamp(&self, width: usize, height: usize) -> Rectangle {
+        let x = self.x.min(width);
+        let y = self.y.min(height);
+        Rectangle {
+            x,
+            y,
+            w: self.w.min(width - x),
+            h: self.h.min(height - y),
+        }
+    }
+
+    pub fn columns(&self) -> Range<usize> {
+        self.x..self.x + self.w
+    }
+
+    pub fn rows(&self) -> Range<usize> {
+        self.y..self.y + self.h
+    }
+
     pub fn random_coords(&self, rng: &mut rand::rngs::SmallRng) -> (usize, usize) {
         let x = rng.gen_range(self.x..self.x + self.w);
         let y = rng.gen_range(self.y..self.y + self.h);
         (x, y)
     }
+
+    // the centers of a grid of columns by rows cells in this rectangle
+    pub fn grid_coords(&self, columns: usize, rows: usize) -> Vec<(usize, usize)> {
+        let mut result = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let x = self.x + (2 * column + 1) * self.w / (2 * columns);
+                let y = self.y + (2 * row + 1) * self.h / (2 * rows);
+                result.push((x, y));
+            }
+        }
+        result
+    }
 }
@@ -1,72 +1,113 @@
-use crate::client_command::ClientCommand;
-use crate::info::WorldStateInfo;
+use crate::client_command::{ClientCommand, IslandCommand, TopologyEdit};
+use crate::events::{EventBus, EventType};
+use crate::follow::{FollowMessage, SplitChoice};
+use crate::info::{FrameDemand, Overview, WorldStateInfo};
+use crate::inspect::ComputerInspection;
+use crate::island::Connection;
+use crate::message::message;
+use crate::metrics::Kind;
+use crate::topology::IslandDescription;
+use crate::view::ViewRequest;
 use axum::{
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
-    extract::{Extension, Query},
-    http::StatusCode,
-    response::{IntoResponse, Json},
-    routing::{get, get_service, post},
+    extract::{Extension, Path, Query},
+    http::{header, StatusCode, Uri},
+    response::{Html, IntoResponse, Json},
+    routing::{delete, get, post, put},
     Router,
 };
+use clap::ValueEnum;
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde_derive::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::{net::SocketAddr, path::PathBuf};
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
-use tower_http::services::ServeDir;
+use tokio::sync::watch;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod ui_assets {
+    include!(concat!(env!("OUT_DIR"), "/ui_assets.rs"));
+}
+
+const NO_UI_PAGE: &str = "<!DOCTYPE html>
+<title>Apilar</title>
+<p>The web UI isn't built into this binary. To include it, build it first:</p>
+<pre>cd ui
+npm install
+npm run build
+cd ..
+cargo build --release</pre>
+<p>Or run it separately with <code>npm run dev</code> in <code>ui</code>.</p>
+";
+
 type WorldStateInfoSender = broadcast::Sender<WorldStateInfo>;
 type WorldStateInfoSharedSender = Arc<WorldStateInfoSender>;
 
 type ClientCommandSender = mpsc::Sender<ClientCommand>;
 
+// the websocket connections, for the metrics
+#[derive(Debug, Default)]
+struct WebsocketClients(AtomicUsize);
+
+// a connection is counted until this is dropped
+struct WebsocketClient(Arc<WebsocketClients>);
+
+impl WebsocketClients {
+    fn connect(self: &Arc<Self>) -> WebsocketClient {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        WebsocketClient(Arc::clone(self))
+    }
+
+    fn count(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for WebsocketClient {
+    fn drop(&mut self) {
+        (self.0).0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 pub async fn serve_task(
     world_state_info_tx: WorldStateInfoSender,
     client_command_tx: ClientCommandSender,
+    events: Arc<EventBus>,
+    frame_demand: Arc<FrameDemand>,
 ) {
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG")
-                .unwrap_or_else(|_| "example_websockets=debug,tower_http=debug".into()),
+            std::env::var("RUST_LOG").unwrap_or_else(|_| "example_websockets=debug".into()),
         ))
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let assets_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets");
-
-    // build our application with some routes
+    // the web UI talks to the API under /api when it's run with `npm run
+    // dev`, so it's available both there and at the top
     let app = Router::new()
-        .fallback(
-            get_service(ServeDir::new(assets_dir).append_index_html_on_directories(true))
-                .handle_error(|error: std::io::Error| async move {
-                    (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        format!("Unhandled internal error: {}", error),
-                    )
-                }),
-        )
-        // routes are matched from bottom to top, so we have to put the fallback at the
-        // top since it matches all routes
-        .route("/ws", get(ws_handler))
-        .route("/disassemble", get(disassemble_handler))
-        .route("/observe", post(observe_handler))
+        .nest("/api", api_routes())
+        .merge(api_routes())
+        .fallback(get(asset_handler))
         // logging so we can see whats going on
         // .layer(
         //     TraceLayer::new_for_http()
         //         .make_span_with(DefaultMakeSpan::default().include_headers(true)),
         // )
         .layer(Extension(Arc::new(world_state_info_tx)))
-        .layer(Extension(client_command_tx));
+        .layer(Extension(client_command_tx))
+        .layer(Extension(events))
+        .layer(Extension(frame_demand))
+        .layer(Extension(Arc::new(WebsocketClients::default())));
 
     let port = get_available_port().unwrap();
 
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
 
-    println!("Apilar server started on {}", addr);
+    message!("Apilar server started on {}", addr);
     // tracing::debug!("listening on {}", addr);
 
     // run it with hyper
@@ -76,6 +117,33 @@ pub async fn serve_task(
         .unwrap();
 }
 
+fn api_routes() -> Router {
+    Router::new()
+        .route("/ws", get(ws_handler))
+        .route("/events", get(events_handler))
+        .route("/follow", get(follow_handler))
+        .route("/disassemble", get(disassemble_handler))
+        .route("/observe", post(observe_handler))
+        .route("/migrations", get(migrations_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/history", get(history_handler))
+        .route("/islands", post(add_island_handler))
+        .route("/islands/:island_id", delete(remove_island_handler))
+        .route("/islands/:island_id/clone", post(clone_island_handler))
+        .route("/islands/:island_id/pause", post(pause_island_handler))
+        .route("/islands/:island_id/resume", post(resume_island_handler))
+        .route("/islands/:island_id/step", post(step_island_handler))
+        .route("/islands/:island_id/speed", post(speed_island_handler))
+        .route(
+            "/islands/:island_id/connections",
+            post(add_connection_handler),
+        )
+        .route(
+            "/islands/:island_id/connections/:connection_id",
+            put(update_connection_handler).delete(remove_connection_handler),
+        )
+}
+
 // taken from https://elliotekj.com/posts/2017/07/25/find-available-tcp-port-rust/
 
 fn get_available_port() -> Option<u16> {
@@ -86,36 +154,73 @@ fn is_port_available(port: u16) -> bool {
     TcpListener::bind(("127.0.0.1", port)).is_ok()
 }
 
+// the UI is a single page, so paths that aren't files get index.html
+fn find_asset<'a>(assets: &[(&'a str, &'a [u8])], path: &str) -> Option<(&'a str, &'a [u8])> {
+    let path = path.trim_start_matches('/');
+    let path = if path.is_empty() { "index.html" } else { path };
+    let found = assets.iter().find(|(name, _)| *name == path);
+    match found {
+        Some(&asset) => Some(asset),
+        None if !path.contains('.') => assets
+            .iter()
+            .find(|(name, _)| *name == "index.html")
+            .copied(),
+        None => None,
+    }
+}
+
+async fn asset_handler(uri: Uri) -> impl IntoResponse {
+    if ui_assets::UI_ASSETS.is_empty() {
+        return Html(NO_UI_PAGE).into_response();
+    }
+    match find_asset(ui_assets::UI_ASSETS, uri.path()) {
+        Some((name, contents)) => {
+            let mime = mime_guess::from_path(name).first_or_octet_stream();
+            ([(header::CONTENT_TYPE, mime.to_string())], contents).into_response()
+        }
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
 #[derive(Debug, Deserialize, Default)]
 struct Coordinates {
     x: usize,
     y: usize,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Serialize)]
 enum DisassembleResponse {
-    Success { code: String },
+    Success(Box<ComputerInspection>),
     Failure { message: String },
 }
 
-async fn disassemble_handler(
-    coordinates: Query<Coordinates>,
-    Extension(client_command_tx): Extension<ClientCommandSender>,
-) -> impl IntoResponse {
+// the world's answer to a command, unless it's gone, such as while it quits
+async fn send_command<T>(
+    client_command_tx: &ClientCommandSender,
+    command: impl FnOnce(oneshot::Sender<T>) -> ClientCommand,
+) -> Result<T, StatusCode> {
     let (resp_tx, resp_rx) = oneshot::channel();
     client_command_tx
-        .send(ClientCommand::Disassemble {
-            x: coordinates.x,
-            y: coordinates.y,
-            respond: resp_tx,
-        })
+        .send(command(resp_tx))
         .await
-        .unwrap(); // XXX unwrap
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
+    resp_rx.await.map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+}
 
-    match resp_rx.await.unwrap() {
-        Ok(code) => Json(DisassembleResponse::Success { code }),
+async fn disassemble_handler(
+    coordinates: Query<Coordinates>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> Result<Json<DisassembleResponse>, StatusCode> {
+    let result = send_command(&client_command_tx, |respond| ClientCommand::Disassemble {
+        x: coordinates.x,
+        y: coordinates.y,
+        respond,
+    })
+    .await?;
+    Ok(match result {
+        Ok(inspection) => Json(DisassembleResponse::Success(Box::new(inspection))),
         Err(message) => Json(DisassembleResponse::Failure { message }),
-    }
+    })
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -127,53 +232,525 @@ struct ObserveQuery {
 async fn observe_handler(
     query: Query<ObserveQuery>,
     Extension(client_command_tx): Extension<ClientCommandSender>,
-) -> impl IntoResponse {
+) -> Result<(), StatusCode> {
     client_command_tx
         .send(ClientCommand::Observe {
             island_id: query.island_id,
         })
         .await
-        .unwrap(); // XXX unwrap
+        .map_err(|_| StatusCode::SERVICE_UNAVAILABLE)
+}
+
+async fn metrics_handler(
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+    Extension(websocket_clients): Extension<Arc<WebsocketClients>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let mut metrics = send_command(&client_command_tx, |respond| ClientCommand::Metrics {
+        respond,
+    })
+    .await?;
+    metrics.single(
+        "apilar_websocket_clients",
+        "Connected websocket clients",
+        Kind::Gauge,
+        websocket_clients.count() as f64,
+    );
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.text().to_string(),
+    ))
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    island_id: usize,
+    // in ticks, inclusive
+    from: Option<u64>,
+    to: Option<u64>,
+    max_samples: Option<usize>,
+}
+
+async fn history_handler(
+    query: Query<HistoryQuery>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let result = send_command(&client_command_tx, |respond| ClientCommand::History {
+        island_id: query.island_id,
+        from: query.from,
+        to: query.to,
+        max_samples: query.max_samples,
+        respond,
+    })
+    .await?;
+    Ok(match result {
+        Ok(history) => Json(history).into_response(),
+        Err(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct MigrationsQuery {
+    // how many recent migrations to include
+    #[serde(default = "default_migrations_limit")]
+    limit: usize,
+}
+
+fn default_migrations_limit() -> usize {
+    100
+}
+
+async fn migrations_handler(
+    query: Query<MigrationsQuery>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let migrations = send_command(&client_command_tx, |respond| ClientCommand::Migrations {
+        limit: query.limit,
+        respond,
+    })
+    .await?;
+    Ok(Json(migrations))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+enum ControlResponse {
+    Success,
+    Failure { message: String },
+}
+
+async fn control_island(
+    client_command_tx: ClientCommandSender,
+    island_id: usize,
+    command: IslandCommand,
+) -> Result<Json<ControlResponse>, StatusCode> {
+    let result = send_command(&client_command_tx, |respond| ClientCommand::ControlIsland {
+        island_id,
+        command,
+        respond,
+    })
+    .await?;
+    Ok(match result {
+        Ok(()) => Json(ControlResponse::Success),
+        Err(message) => Json(ControlResponse::Failure { message }),
+    })
+}
+
+async fn pause_island_handler(
+    Path(island_id): Path<usize>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    control_island(client_command_tx, island_id, IslandCommand::Pause).await
+}
+
+async fn resume_island_handler(
+    Path(island_id): Path<usize>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    control_island(client_command_tx, island_id, IslandCommand::Resume).await
+}
+
+#[derive(Debug, Deserialize)]
+struct StepQuery {
+    #[serde(default = "default_step_ticks")]
+    ticks: u64,
+}
+
+fn default_step_ticks() -> u64 {
+    1
+}
+
+async fn step_island_handler(
+    Path(island_id): Path<usize>,
+    query: Query<StepQuery>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    control_island(
+        client_command_tx,
+        island_id,
+        IslandCommand::Step { ticks: query.ticks },
+    )
+    .await
+}
+
+#[derive(Debug, Deserialize)]
+struct SpeedQuery {
+    // no limit if left out
+    ticks_per_second: Option<f64>,
+}
+
+async fn speed_island_handler(
+    Path(island_id): Path<usize>,
+    query: Query<SpeedQuery>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    control_island(
+        client_command_tx,
+        island_id,
+        IslandCommand::SpeedLimit {
+            ticks_per_second: query.ticks_per_second,
+        },
+    )
+    .await
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+enum EditResponse {
+    Success { id: usize },
+    Failure { message: String },
+}
+
+async fn edit_topology(
+    client_command_tx: ClientCommandSender,
+    edit: TopologyEdit,
+) -> Result<Json<EditResponse>, StatusCode> {
+    let result = send_command(&client_command_tx, |respond| ClientCommand::EditTopology {
+        edit: Box::new(edit),
+        respond,
+    })
+    .await?;
+    Ok(match result {
+        Ok(id) => Json(EditResponse::Success { id }),
+        Err(message) => Json(EditResponse::Failure { message }),
+    })
+}
+
+async fn add_island_handler(
+    Json(island): Json<IslandDescription>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    edit_topology(client_command_tx, TopologyEdit::AddIsland { island }).await
+}
+
+async fn clone_island_handler(
+    Path(island_id): Path<usize>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    edit_topology(client_command_tx, TopologyEdit::CloneIsland { island_id }).await
+}
+
+async fn remove_island_handler(
+    Path(island_id): Path<usize>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    edit_topology(client_command_tx, TopologyEdit::RemoveIsland { island_id }).await
+}
+
+async fn add_connection_handler(
+    Path(island_id): Path<usize>,
+    Json(connection): Json<Connection>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    edit_topology(
+        client_command_tx,
+        TopologyEdit::AddConnection {
+            island_id,
+            connection,
+        },
+    )
+    .await
+}
+
+async fn update_connection_handler(
+    Path((island_id, connection_id)): Path<(usize, usize)>,
+    Json(connection): Json<Connection>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    edit_topology(
+        client_command_tx,
+        TopologyEdit::UpdateConnection {
+            island_id,
+            connection_id,
+            connection,
+        },
+    )
+    .await
+}
+
+async fn remove_connection_handler(
+    Path((island_id, connection_id)): Path<(usize, usize)>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+) -> impl IntoResponse {
+    edit_topology(
+        client_command_tx,
+        TopologyEdit::RemoveConnection {
+            island_id,
+            connection_id,
+        },
+    )
+    .await
 }
+
 async fn ws_handler(
     ws: WebSocketUpgrade,
     Extension(world_state_info_tx): Extension<WorldStateInfoSharedSender>,
     Extension(client_command_tx): Extension<ClientCommandSender>,
+    Extension(frame_demand): Extension<Arc<FrameDemand>>,
+    Extension(websocket_clients): Extension<Arc<WebsocketClients>>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, world_state_info_tx, client_command_tx))
+    ws.on_upgrade(move |socket| {
+        let client = websocket_clients.connect();
+        handle_socket(
+            socket,
+            world_state_info_tx,
+            client_command_tx,
+            frame_demand,
+            client,
+        )
+    })
 }
 
-async fn handle_socket<'a>(
+async fn handle_socket(
     socket: WebSocket,
     world_state_info_tx: WorldStateInfoSharedSender,
     client_command_tx: ClientCommandSender,
+    frame_demand: Arc<FrameDemand>,
+    _client: WebsocketClient,
 ) {
     let (mut sender, mut receiver) = socket.split();
 
+    // a client gets every location of the observed island, until it sends a
+    // view request. after that it only gets the view, until it sends "full"
+    let (view_tx, view_rx) = watch::channel(None);
+    let start_stop_command_tx = client_command_tx.clone();
     let start_stop_task = tokio::spawn(async move {
         loop {
             if let Some(Ok(Message::Text(msg))) = receiver.next().await {
+                // the world may be gone already
                 if msg == "stop" {
-                    client_command_tx.send(ClientCommand::Stop).await.unwrap();
+                    let _ = start_stop_command_tx.send(ClientCommand::Stop).await;
                 } else if msg == "start" {
-                    client_command_tx.send(ClientCommand::Start).await.unwrap();
+                    let _ = start_stop_command_tx.send(ClientCommand::Start).await;
+                } else if msg == "full" {
+                    let _ = view_tx.send(None);
+                } else if let Ok(request) = serde_json::from_str::<ViewRequest>(&msg) {
+                    let _ = view_tx.send(Some(request));
                 }
             }
         }
     });
 
     let mut world_state_info_rx = world_state_info_tx.subscribe();
+    let mut full_frames = Some(frame_demand.want_full_frames());
+    let mut overview_demand: Option<Overview> = None;
     loop {
-        if let Ok(value) = world_state_info_rx.recv().await {
+        if let Ok(mut value) = world_state_info_rx.recv().await {
+            let request = view_rx.borrow().clone();
+            match request {
+                Some(request) => {
+                    full_frames = None;
+                    // overviews are worked out with every frame for the
+                    // clients that want them, and shared
+                    let key = request.overview_key();
+                    if overview_demand.as_ref().map(|demand| demand.key) != Some(key) {
+                        overview_demand = Some(frame_demand.want_overview(key));
+                    }
+                    let overview = value
+                        .overviews
+                        .iter()
+                        .find(|overview| (overview.block_size, overview.aggregate) == key)
+                        .cloned();
+                    let view = send_command(&client_command_tx, |respond| ClientCommand::View {
+                        request,
+                        overview,
+                        respond,
+                    })
+                    .await;
+                    let view = match view {
+                        Ok(view) => view,
+                        Err(_) => {
+                            start_stop_task.abort();
+                            return;
+                        }
+                    };
+                    value.locations = Vec::new();
+                    value.view = Some(view);
+                }
+                None => {
+                    overview_demand = None;
+                    if full_frames.is_none() {
+                        full_frames = Some(frame_demand.want_full_frames());
+                    }
+                }
+            }
             // XXX unwrap here, what if this fails?
             let json = serde_json::to_string(&value).unwrap();
 
             if sender.send(Message::Text(json)).await.is_err() {
                 // XXX this isn't the world's best error handling either
-                println!("client disconnected");
+                message!("client disconnected");
                 start_stop_task.abort();
                 return;
             }
         }
     }
 }
+
+#[derive(Debug, Deserialize, Default)]
+struct EventsQuery {
+    // comma separated, all events if not given
+    types: Option<String>,
+    island_id: Option<usize>,
+}
+
+async fn events_handler(
+    ws: WebSocketUpgrade,
+    query: Query<EventsQuery>,
+    Extension(events): Extension<Arc<EventBus>>,
+    Extension(websocket_clients): Extension<Arc<WebsocketClients>>,
+) -> impl IntoResponse {
+    let mut types = Vec::new();
+    if let Some(names) = &query.types {
+        for name in names.split(',') {
+            match EventType::from_str(name, true) {
+                Ok(event_type) => types.push(event_type),
+                Err(_) => {
+                    return (
+                        StatusCode::BAD_REQUEST,
+                        format!("Unknown event type: {}", name),
+                    )
+                        .into_response()
+                }
+            }
+        }
+    }
+    let island_id = query.island_id;
+    ws.on_upgrade(move |socket| {
+        let client = websocket_clients.connect();
+        handle_events_socket(socket, events, types, island_id, client)
+    })
+    .into_response()
+}
+
+async fn handle_events_socket(
+    socket: WebSocket,
+    events: Arc<EventBus>,
+    types: Vec<EventType>,
+    island_id: Option<usize>,
+    _client: WebsocketClient,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut events_rx = events.subscribe();
+    loop {
+        tokio::select! {
+            message = receiver.next() => {
+                // we don't expect anything from the client, so this is it closing
+                if !matches!(message, Some(Ok(_))) {
+                    break;
+                }
+            }
+            event = events_rx.recv() => {
+                let event = match event {
+                    Ok(event) => event,
+                    // a slow client misses some events
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if !types.is_empty() && !types.contains(&event.kind.event_type()) {
+                    continue;
+                }
+                if island_id.is_some_and(|island_id| island_id != event.island_id) {
+                    continue;
+                }
+                let json = serde_json::to_string(&event).unwrap();
+                if sender.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    events.unsubscribe();
+}
+
+#[derive(Debug, Deserialize)]
+struct FollowQuery {
+    island_id: usize,
+    x: usize,
+    y: usize,
+    #[serde(default)]
+    split: SplitChoice,
+}
+
+async fn follow_handler(
+    ws: WebSocketUpgrade,
+    query: Query<FollowQuery>,
+    Extension(client_command_tx): Extension<ClientCommandSender>,
+    Extension(websocket_clients): Extension<Arc<WebsocketClients>>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let result = send_command(&client_command_tx, |respond| ClientCommand::Follow {
+        island_id: query.island_id,
+        x: query.x,
+        y: query.y,
+        split: query.split,
+        respond,
+    })
+    .await?;
+    Ok(match result {
+        Ok(follow_rx) => ws
+            .on_upgrade(move |socket| {
+                let client = websocket_clients.connect();
+                handle_follow_socket(socket, follow_rx, client)
+            })
+            .into_response(),
+        Err(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+    })
+}
+
+async fn handle_follow_socket(
+    socket: WebSocket,
+    mut follow_rx: mpsc::Receiver<FollowMessage>,
+    _client: WebsocketClient,
+) {
+    let (mut sender, mut receiver) = socket.split();
+    loop {
+        tokio::select! {
+            message = receiver.next() => {
+                // we don't expect anything from the client, so this is it closing
+                if !matches!(message, Some(Ok(_))) {
+                    break;
+                }
+            }
+            message = follow_rx.recv() => {
+                let message = match message {
+                    Some(message) => message,
+                    None => break,
+                };
+                let json = serde_json::to_string(&message).unwrap();
+                if sender.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
+                if matches!(message, FollowMessage::Stop { .. }) {
+                    break;
+                }
+            }
+        }
+    }
+    // the island stops sending once this is dropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket_clients() {
+        let clients = Arc::new(WebsocketClients::default());
+        let first = clients.connect();
+        let second = clients.connect();
+        assert_eq!(clients.count(), 2);
+        drop(first);
+        drop(second);
+        assert_eq!(clients.count(), 0);
+    }
+
+    #[test]
+    fn test_find_asset() {
+        let assets: &[(&str, &[u8])] = &[
+            ("index.html", b"index"),
+            ("assets/index.1234.js", b"script"),
+        ];
+        let name = |path| find_asset(assets, path).map(|(name, _)| name);
+        assert_eq!(name("/"), Some("index.html"));
+        assert_eq!(name("/assets/index.1234.js"), Some("assets/index.1234.js"));
+        assert_eq!(name("/islands"), Some("index.html"));
+        assert_eq!(name("/favicon.ico"), None);
+    }
+}
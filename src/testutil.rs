@@ -1,4 +1,5 @@
 use crate::assembler::Assembler;
+use crate::habitat::HabitatConfig;
 use crate::instruction::Metabolism;
 use crate::memory::Memory;
 use crate::processor::Processor;
@@ -18,7 +19,7 @@ pub fn execute(text: &str) -> Exec {
     let assembler = Assembler::new();
     let mut memory = Memory::new(1000);
     let mut wants = Wants::new();
-    let amount = assembler.assemble(text, &mut memory, 0);
+    let amount = assembler.assemble(text, &mut memory, 0).unwrap();
     let mut processor = Processor::new(0);
     let mut rng = SmallRng::from_seed([0; 32]);
     processor.execute_amount(
@@ -45,7 +46,7 @@ pub fn execute_lines(text: &str) -> Exec {
     let assembler = Assembler::new();
     let mut memory = Memory::new(1000);
     let mut wants = Wants::new();
-    let amount = assembler.line_assemble(text, &mut memory, 0);
+    let amount = assembler.line_assemble(text, &mut memory, 0).unwrap();
     let mut processor = Processor::new(0);
     let mut rng = SmallRng::from_seed([0; 32]);
     processor.execute_amount(
@@ -67,3 +68,27 @@ pub fn execute_lines(text: &str) -> Exec {
         rng,
     }
 }
+
+// a typical island configuration
+pub fn habitat_config() -> HabitatConfig {
+    serde_json::from_str(
+        r#"{
+            "instructions_per_update": 10,
+            "max_processors": 10,
+            "mutation_frequency": 10000,
+            "mutation": {
+              "overwrite_amount": 1,
+              "insert_amount": 1,
+              "delete_amount": 0,
+              "stack_amount": 1
+            },
+            "death": { "rate": 20000, "memory_size": 8192 },
+            "metabolism": {
+              "max_eat_amount": 128,
+              "max_grow_amount": 16,
+              "max_shrink_amount": 16
+            }
+        }"#,
+    )
+    .unwrap()
+}
@@ -1,7 +1,7 @@
 use serde_derive::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Ticks(pub u64);
 
 impl fmt::Display for Ticks {
@@ -23,6 +23,6 @@ impl Ticks {
     }
 
     pub fn is_at(&self, ticks: Ticks) -> bool {
-        self.0 % ticks.0 == 0
+        self.0.is_multiple_of(ticks.0)
     }
 }
@@ -1,16 +1,30 @@
 use crate::{
-    habitat::HabitatConfig,
-    island::{Connection, Disaster},
+    assembler::{text_to_words, Assembler},
+    computer::Computer,
+    habitat::{Habitat, HabitatConfig},
+    island::{
+        Connection, Disaster, Extinction, ExtinctionPolicy, Island, IslandSettings, SeedPattern,
+    },
+    rectangle::Rectangle,
+    ticks::Ticks,
 };
+use anyhow::{anyhow, Result};
 use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Topology {
     pub islands: Vec<IslandDescription>,
     pub computers: Vec<ComputerDescription>,
+    #[serde(default)]
+    pub seeds: Vec<SeedDescription>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct IslandDescription {
     pub config: HabitatConfig,
     pub width: usize,
@@ -18,14 +32,994 @@ pub struct IslandDescription {
     pub resources: u64,
     pub disaster: Option<Disaster>,
     pub connections: Vec<Connection>,
+    pub extinction: Option<Extinction>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProgramDescription {
+    // the program is either loaded from a file, or given inline as code
+    pub filename: Option<String>,
+    pub code: Option<String>,
+    pub resources: u64,
+    // memory is padded up to this size; by default it's as long as the program
+    pub memory_size: Option<usize>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ComputerDescription {
     pub island_id: usize,
-    pub filename: String,
     pub x: usize,
     pub y: usize,
-    pub resources: u64,
-    pub memory_size: Option<usize>,
+    #[serde(flatten)]
+    pub program: ProgramDescription,
+}
+
+// places copies of a program on one or more islands
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SeedDescription {
+    pub islands: Vec<usize>,
+    pub pattern: SeedPattern,
+    // if given, the seed is placed again every so many ticks
+    pub immigration_frequency: Option<Ticks>,
+    #[serde(flatten)]
+    pub program: ProgramDescription,
+}
+
+// a problem in a topology, with the JSON path where it occurs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid topology ({} problems):", self.0.len())?;
+        for error in &self.0 {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl Topology {
+    // read a topology from JSON. islands are built up from the top-level
+    // `defaults`, then any `preset`s they name from `presets`, then whatever
+    // the island itself gives. objects are merged, anything else is replaced
+    pub fn from_reader<R: Read>(reader: R) -> Result<Topology> {
+        let mut value: Value = serde_json::from_reader(reader)?;
+        resolve_islands(&mut value)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn single_island(island: IslandDescription, program: ProgramDescription) -> Topology {
+        let computer = ComputerDescription {
+            island_id: 0,
+            x: island.width / 2,
+            y: island.height / 2,
+            program,
+        };
+        Topology {
+            islands: vec![island],
+            computers: vec![computer],
+            seeds: Vec::new(),
+        }
+    }
+
+    // verify the whole topology up front, so that we report every problem
+    // instead of panicking somewhere during the run
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        self.validate_seeded(|island_id| {
+            self.computers
+                .iter()
+                .any(|computer| computer.island_id == island_id)
+                || self
+                    .seeds
+                    .iter()
+                    .any(|seed| seed.islands.contains(&island_id))
+        })
+    }
+
+    // islands of a running world keep their seed computers themselves, so
+    // whether an island has any is up to the caller
+    pub fn validate_seeded(
+        &self,
+        has_seed: impl Fn(usize) -> bool,
+    ) -> Result<(), ValidationErrors> {
+        let mut errors = Vec::new();
+
+        if self.islands.is_empty() {
+            errors.push(error("islands", "at least one island is required".into()));
+        }
+
+        for (island_id, island) in self.islands.iter().enumerate() {
+            island.validate(island_id, &self.islands, &mut errors);
+        }
+
+        let mut seeded: Vec<(usize, usize, usize, usize)> = Vec::new();
+        for (computer_id, computer) in self.computers.iter().enumerate() {
+            let path = format!("computers[{}]", computer_id);
+            computer.validate(&path, &self.islands, &mut errors);
+            if let Some((_, _, _, other_id)) = seeded.iter().find(|(island_id, x, y, _)| {
+                *island_id == computer.island_id && *x == computer.x && *y == computer.y
+            }) {
+                errors.push(error(
+                    &path,
+                    format!(
+                        "location ({}, {}) is already seeded by computers[{}]",
+                        computer.x, computer.y, other_id
+                    ),
+                ));
+            }
+            seeded.push((computer.island_id, computer.x, computer.y, computer_id));
+        }
+
+        for (seed_id, seed) in self.seeds.iter().enumerate() {
+            seed.validate(&format!("seeds[{}]", seed_id), &self.islands, &mut errors);
+        }
+
+        for (island_id, island) in self.islands.iter().enumerate() {
+            if let Some(extinction) = &island.extinction {
+                self.validate_extinction(island_id, extinction, &has_seed, &mut errors);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(errors))
+        }
+    }
+}
+
+impl Topology {
+    fn validate_extinction(
+        &self,
+        island_id: usize,
+        extinction: &Extinction,
+        has_seed: impl Fn(usize) -> bool,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let path = format!("islands[{}].extinction", island_id);
+        if extinction.check_frequency.is_zero() {
+            errors.push(error(
+                &format!("{}.check_frequency", path),
+                "must be greater than 0".into(),
+            ));
+        }
+        let pattern_path = format!("{}.pattern", path);
+        validate_pattern(&pattern_path, &extinction.pattern, errors);
+        validate_pattern_rect(
+            &pattern_path,
+            &extinction.pattern,
+            &self.islands[island_id],
+            island_id,
+            errors,
+        );
+        match extinction.policy {
+            ExtinctionPolicy::Seed => {
+                if !has_seed(island_id) {
+                    errors.push(error(
+                        &format!("{}.policy", path),
+                        format!("island {} has no seed computers", island_id),
+                    ));
+                }
+            }
+            ExtinctionPolicy::Neighbor => {
+                let has_neighbor = self.islands.iter().enumerate().any(|(other_id, other)| {
+                    other_id != island_id
+                        && (self.islands[island_id]
+                            .connections
+                            .iter()
+                            .any(|connection| connection.to_id == other_id)
+                            || other
+                                .connections
+                                .iter()
+                                .any(|connection| connection.to_id == island_id))
+                });
+                if !has_neighbor {
+                    errors.push(error(
+                        &format!("{}.policy", path),
+                        format!("island {} is not connected to other islands", island_id),
+                    ));
+                }
+            }
+            ExtinctionPolicy::Genebank => {}
+        }
+    }
+}
+
+impl ProgramDescription {
+    pub fn source(&self) -> Result<String> {
+        match (&self.filename, &self.code) {
+            (Some(filename), None) => Ok(fs::read_to_string(filename)?),
+            (None, Some(code)) => Ok(code.clone()),
+            (Some(_), Some(_)) => Err(anyhow!("Both filename and code given")),
+            (None, None) => Err(anyhow!("Either filename or code is required")),
+        }
+    }
+
+    pub fn computer(&self, assembler: &Assembler) -> Result<Computer> {
+        let contents = self.source()?;
+        let words = text_to_words(&contents);
+        let memory_size = self.memory_size.unwrap_or(words.len());
+        let mut computer = Computer::new(memory_size, self.resources);
+        assembler
+            .assemble_words(words, &mut computer.memory, 0)
+            .map_err(|message| anyhow!(message))?;
+        computer.add_processor(0);
+        Ok(computer)
+    }
+
+    fn validate(&self, path: &str, errors: &mut Vec<ValidationError>) {
+        match (&self.filename, &self.code) {
+            (Some(filename), None) if !Path::new(filename).is_file() => {
+                errors.push(error(
+                    &format!("{}.filename", path),
+                    format!("file `{}` not found", filename),
+                ));
+                return;
+            }
+            (Some(_), Some(_)) => {
+                errors.push(error(path, "give either filename or code, not both".into()));
+                return;
+            }
+            (None, None) => {
+                errors.push(error(path, "either filename or code is required".into()));
+                return;
+            }
+            _ => {}
+        }
+        let source = match self.source() {
+            Ok(source) => source,
+            Err(err) => {
+                errors.push(error(path, err.to_string()));
+                return;
+            }
+        };
+        let words = text_to_words(&source);
+        let unknown = Assembler::new().unknown_words(&words);
+        if !unknown.is_empty() {
+            let field = if self.code.is_some() {
+                "code"
+            } else {
+                "filename"
+            };
+            errors.push(error(
+                &format!("{}.{}", path, field),
+                format!("unknown instructions: {}", unknown.join(", ")),
+            ));
+        }
+        if let Some(memory_size) = self.memory_size {
+            let program_size = words.len();
+            if memory_size < program_size {
+                errors.push(error(
+                    &format!("{}.memory_size", path),
+                    format!(
+                        "{} is too small for program of {} instructions",
+                        memory_size, program_size
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+impl IslandDescription {
+    fn validate(
+        &self,
+        island_id: usize,
+        islands: &[IslandDescription],
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let path = format!("islands[{}]", island_id);
+        if self.width == 0 {
+            errors.push(error(
+                &format!("{}.width", path),
+                "must be greater than 0".into(),
+            ));
+        }
+        if self.height == 0 {
+            errors.push(error(
+                &format!("{}.height", path),
+                "must be greater than 0".into(),
+            ));
+        }
+        if self.config.mutation_frequency.0 == 0 {
+            errors.push(error(
+                &format!("{}.config.mutation_frequency", path),
+                "must be greater than 0".into(),
+            ));
+        }
+        if self.config.death.rate == 0 {
+            errors.push(error(
+                &format!("{}.config.death.rate", path),
+                "must be greater than 0".into(),
+            ));
+        }
+        if let Some(disaster) = &self.disaster {
+            if disaster.frequency.0 == 0 {
+                errors.push(error(
+                    &format!("{}.disaster.frequency", path),
+                    "must be greater than 0".into(),
+                ));
+            }
+        }
+        for (connection_id, connection) in self.connections.iter().enumerate() {
+            let path = format!("{}.connections[{}]", path, connection_id);
+            validate_rect(
+                &format!("{}.from_rect", path),
+                &connection.from_rect,
+                self,
+                island_id,
+                errors,
+            );
+            match islands.get(connection.to_id) {
+                Some(to_island) => validate_rect(
+                    &format!("{}.to_rect", path),
+                    &connection.to_rect,
+                    to_island,
+                    connection.to_id,
+                    errors,
+                ),
+                None => errors.push(error(
+                    &format!("{}.to_id", path),
+                    unknown_island(connection.to_id, islands.len()),
+                )),
+            }
+            match (connection.transmit_frequency, connection.transmit_ticks) {
+                (Some(_), Some(_)) => errors.push(error(
+                    &path,
+                    "only one of transmit_frequency and transmit_ticks can be given".into(),
+                )),
+                (None, None) => errors.push(error(
+                    &path,
+                    "either transmit_frequency or transmit_ticks is required".into(),
+                )),
+                (None, Some(ticks)) if ticks.0 == 0 => errors.push(error(
+                    &format!("{}.transmit_ticks", path),
+                    "must be greater than 0".into(),
+                )),
+                (Some(frequency), None) if frequency.is_zero() => errors.push(error(
+                    &format!("{}.transmit_frequency", path),
+                    "must be greater than 0".into(),
+                )),
+                _ => {}
+            }
+            if connection.amount == 0 {
+                errors.push(error(
+                    &format!("{}.amount", path),
+                    "must be greater than 0".into(),
+                ));
+            }
+            if let Some(filter) = &connection.filter {
+                if filter.min_memory_size > filter.max_memory_size
+                    && filter.max_memory_size.is_some()
+                {
+                    errors.push(error(
+                        &format!("{}.filter.max_memory_size", path),
+                        "must not be less than min_memory_size".into(),
+                    ));
+                }
+                if filter.min_processors > filter.max_processors && filter.max_processors.is_some()
+                {
+                    errors.push(error(
+                        &format!("{}.filter.max_processors", path),
+                        "must not be less than min_processors".into(),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl IslandDescription {
+    // an empty island as described
+    pub fn island(&self) -> Island {
+        Island::new(
+            Habitat::new(self.width, self.height, self.resources),
+            self.config.clone(),
+            self.disaster.clone(),
+            self.connections.clone(),
+            self.extinction.clone(),
+        )
+    }
+
+    pub fn set_settings(&mut self, settings: IslandSettings) {
+        self.config = settings.config;
+        self.disaster = settings.disaster;
+        self.connections = settings.connections;
+        self.extinction = settings.extinction;
+    }
+}
+
+// describes an existing island, so that it can be validated. free
+// resources aren't tracked
+impl From<&Island> for IslandDescription {
+    fn from(island: &Island) -> IslandDescription {
+        let settings = island.settings();
+        IslandDescription {
+            config: settings.config,
+            width: island.habitat.width,
+            height: island.habitat.height,
+            resources: 0,
+            disaster: settings.disaster,
+            connections: settings.connections,
+            extinction: settings.extinction,
+        }
+    }
+}
+
+impl ComputerDescription {
+    fn validate(
+        &self,
+        path: &str,
+        islands: &[IslandDescription],
+        errors: &mut Vec<ValidationError>,
+    ) {
+        match islands.get(self.island_id) {
+            Some(island) => {
+                if self.x >= island.width || self.y >= island.height {
+                    errors.push(error(
+                        path,
+                        format!(
+                            "location ({}, {}) is outside island {} ({}x{})",
+                            self.x, self.y, self.island_id, island.width, island.height
+                        ),
+                    ));
+                }
+            }
+            None => errors.push(error(
+                &format!("{}.island_id", path),
+                unknown_island(self.island_id, islands.len()),
+            )),
+        }
+        self.program.validate(path, errors);
+    }
+}
+
+impl SeedDescription {
+    fn validate(
+        &self,
+        path: &str,
+        islands: &[IslandDescription],
+        errors: &mut Vec<ValidationError>,
+    ) {
+        if self.islands.is_empty() {
+            errors.push(error(
+                &format!("{}.islands", path),
+                "at least one island is required".into(),
+            ));
+        }
+        let pattern_path = format!("{}.pattern", path);
+        validate_pattern(&pattern_path, &self.pattern, errors);
+        for (i, island_id) in self.islands.iter().enumerate() {
+            match islands.get(*island_id) {
+                Some(island) => {
+                    validate_pattern_rect(&pattern_path, &self.pattern, island, *island_id, errors)
+                }
+                None => errors.push(error(
+                    &format!("{}.islands[{}]", path, i),
+                    unknown_island(*island_id, islands.len()),
+                )),
+            }
+        }
+        if let Some(frequency) = self.immigration_frequency {
+            if frequency.0 == 0 {
+                errors.push(error(
+                    &format!("{}.immigration_frequency", path),
+                    "must be greater than 0".into(),
+                ));
+            }
+        }
+        self.program.validate(path, errors);
+    }
+}
+
+fn resolve_islands(value: &mut Value) -> Result<()> {
+    let topology = match value.as_object_mut() {
+        Some(topology) => topology,
+        // let deserialization complain
+        None => return Ok(()),
+    };
+    let defaults = topology
+        .remove("defaults")
+        .unwrap_or_else(|| Value::Object(Map::new()));
+    let presets = topology
+        .remove("presets")
+        .unwrap_or_else(|| Value::Object(Map::new()));
+    let islands = match topology.get_mut("islands") {
+        Some(Value::Array(islands)) => islands,
+        _ => return Ok(()),
+    };
+    for (island_id, island) in islands.iter_mut().enumerate() {
+        let preset_names = match island
+            .as_object_mut()
+            .and_then(|island| island.remove("preset"))
+        {
+            None => Vec::new(),
+            Some(Value::String(name)) => vec![name],
+            Some(Value::Array(names)) => names
+                .into_iter()
+                .map(|name| match name {
+                    Value::String(name) => Ok(name),
+                    _ => Err(anyhow!("islands[{}].preset: expected a name", island_id)),
+                })
+                .collect::<Result<Vec<String>>>()?,
+            Some(_) => {
+                return Err(anyhow!(
+                    "islands[{}].preset: expected a name or a list of names",
+                    island_id
+                ))
+            }
+        };
+        let mut resolved = defaults.clone();
+        for name in preset_names {
+            let preset = presets.get(&name).ok_or_else(|| {
+                anyhow!("islands[{}].preset: unknown preset `{}`", island_id, name)
+            })?;
+            merge(&mut resolved, preset);
+        }
+        merge(&mut resolved, island);
+        *island = resolved;
+    }
+    Ok(())
+}
+
+fn merge(target: &mut Value, source: &Value) {
+    match (target, source) {
+        (Value::Object(target), Value::Object(source)) => {
+            for (key, value) in source {
+                merge(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, source) => *target = source.clone(),
+    }
+}
+
+fn validate_pattern(path: &str, pattern: &SeedPattern, errors: &mut Vec<ValidationError>) {
+    match pattern {
+        SeedPattern::Scatter { amount, .. } => {
+            if *amount == 0 {
+                errors.push(error(
+                    &format!("{}.amount", path),
+                    "must be greater than 0".into(),
+                ));
+            }
+        }
+        SeedPattern::Grid { columns, rows, .. } => {
+            if *columns == 0 {
+                errors.push(error(
+                    &format!("{}.columns", path),
+                    "must be greater than 0".into(),
+                ));
+            }
+            if *rows == 0 {
+                errors.push(error(
+                    &format!("{}.rows", path),
+                    "must be greater than 0".into(),
+                ));
+            }
+        }
+    }
+}
+
+fn validate_pattern_rect(
+    path: &str,
+    pattern: &SeedPattern,
+    island: &IslandDescription,
+    island_id: usize,
+    errors: &mut Vec<ValidationError>,
+) {
+    if let Some(rect) = pattern.rect() {
+        validate_rect(&format!("{}.rect", path), rect, island, island_id, errors);
+    }
+}
+
+fn validate_rect(
+    path: &str,
+    rect: &Rectangle,
+    island: &IslandDescription,
+    island_id: usize,
+    errors: &mut Vec<ValidationError>,
+) {
+    if rect.is_empty() {
+        errors.push(error(path, "rectangle must have a width and height".into()));
+    } else if !rect.fits(island.width, island.height) {
+        errors.push(error(
+            path,
+            format!(
+                "rectangle doesn't fit in island {} ({}x{})",
+                island_id, island.width, island.height
+            ),
+        ));
+    }
+}
+
+fn unknown_island(island_id: usize, islands_amount: usize) -> String {
+    format!(
+        "island {} does not exist (there are {} islands)",
+        island_id, islands_amount
+    )
+}
+
+fn error(path: &str, message: String) -> ValidationError {
+    ValidationError {
+        path: path.to_string(),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::habitat_config;
+
+    fn topology(json: &str) -> Topology {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn config() -> String {
+        serde_json::to_string(&habitat_config()).unwrap()
+    }
+
+    fn paths(topology: &Topology) -> Vec<String> {
+        match topology.validate() {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.0.into_iter().map(|error| error.path).collect(),
+        }
+    }
+
+    #[test]
+    fn test_valid() {
+        let topology = topology(&format!(
+            r#"{{
+            "islands": [
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [
+                     {{ "from_rect": {{ "x": 0, "y": 0, "w": 10, "h": 10 }},
+                        "to_rect": {{ "x": 5, "y": 5, "w": 5, "h": 5 }},
+                        "to_id": 0, "transmit_frequency": 1 }}
+                   ] }}
+            ],
+            "computers": [
+                {{ "island_id": 0, "filename": "sample_code/head-world-replicator.apil",
+                   "x": 9, "y": 9, "resources": 10 }}
+            ]
+        }}"#,
+            config = config()
+        ));
+        assert!(topology.validate().is_ok());
+    }
+
+    #[test]
+    fn test_no_islands() {
+        let topology = topology(r#"{ "islands": [], "computers": [] }"#);
+        assert_eq!(paths(&topology), vec!["islands"]);
+    }
+
+    #[test]
+    fn test_bad_connections() {
+        let topology = topology(&format!(
+            r#"{{
+            "islands": [
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [
+                     {{ "from_rect": {{ "x": 5, "y": 0, "w": 10, "h": 10 }},
+                        "to_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_id": 3, "transmit_frequency": 1 }},
+                     {{ "from_rect": {{ "x": 0, "y": 0, "w": 0, "h": 10 }},
+                        "to_rect": {{ "x": 0, "y": 10, "w": 1, "h": 1 }},
+                        "to_id": 0, "transmit_frequency": 1 }}
+                   ] }}
+            ],
+            "computers": []
+        }}"#,
+            config = config()
+        ));
+        assert_eq!(
+            paths(&topology),
+            vec![
+                "islands[0].connections[0].from_rect",
+                "islands[0].connections[0].to_id",
+                "islands[0].connections[1].from_rect",
+                "islands[0].connections[1].to_rect",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bad_migration() {
+        let topology = topology(&format!(
+            r#"{{
+            "islands": [
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [
+                     {{ "from_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_id": 0, "transmit_frequency": 1, "transmit_ticks": 100 }},
+                     {{ "from_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_id": 0, "amount": 0 }},
+                     {{ "from_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_id": 0, "transmit_ticks": 0, "mode": "copy" }},
+                     {{ "from_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_id": 0, "transmit_ticks": 10,
+                        "filter": {{ "min_memory_size": 10, "max_memory_size": 5 }} }},
+                     {{ "from_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_id": 0, "transmit_frequency": 0 }}
+                   ] }}
+            ],
+            "computers": []
+        }}"#,
+            config = config()
+        ));
+        assert_eq!(
+            paths(&topology),
+            vec![
+                "islands[0].connections[0]",
+                "islands[0].connections[1]",
+                "islands[0].connections[1].amount",
+                "islands[0].connections[2].transmit_ticks",
+                "islands[0].connections[3].filter.max_memory_size",
+                "islands[0].connections[4].transmit_frequency",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bad_computers() {
+        let topology = topology(&format!(
+            r#"{{
+            "islands": [
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [] }}
+            ],
+            "computers": [
+                {{ "island_id": 0, "filename": "sample_code/head-world-replicator.apil",
+                   "x": 10, "y": 0, "resources": 10 }},
+                {{ "island_id": 1, "filename": "sample_code/head-world-replicator.apil",
+                   "x": 0, "y": 0, "resources": 10 }},
+                {{ "island_id": 0, "filename": "does-not-exist.apil",
+                   "x": 1, "y": 1, "resources": 10 }},
+                {{ "island_id": 0, "filename": "sample_code/head-world-replicator.apil",
+                   "x": 1, "y": 1, "resources": 10 }}
+            ]
+        }}"#,
+            config = config()
+        ));
+        assert_eq!(
+            paths(&topology),
+            vec![
+                "computers[0]",
+                "computers[1].island_id",
+                "computers[2].filename",
+                "computers[3]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_computer_code_and_memory_size() {
+        let topology = topology(&format!(
+            r#"{{
+            "islands": [
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [] }}
+            ],
+            "computers": [
+                {{ "island_id": 0, "code": "N1\nN2 # comment\nADD",
+                   "x": 0, "y": 0, "resources": 10, "memory_size": 3 }},
+                {{ "island_id": 0, "code": "N1\nN2\nADD",
+                   "x": 1, "y": 0, "resources": 10, "memory_size": 2 }},
+                {{ "island_id": 0, "x": 2, "y": 0, "resources": 10 }},
+                {{ "island_id": 0, "code": "N1",
+                   "filename": "sample_code/head-world-replicator.apil",
+                   "x": 3, "y": 0, "resources": 10 }}
+            ]
+        }}"#,
+            config = config()
+        ));
+        assert_eq!(
+            paths(&topology),
+            vec!["computers[1].memory_size", "computers[2]", "computers[3]"]
+        );
+        assert_eq!(
+            topology.computers[0].program.source().unwrap(),
+            "N1\nN2 # comment\nADD"
+        );
+    }
+
+    #[test]
+    fn test_unknown_instructions() {
+        let filename =
+            std::env::temp_dir().join(format!("apilar-test-unknown-{}.apil", std::process::id()));
+        fs::write(&filename, "N1\nFOO # typo\n").unwrap();
+        let topology = topology(&format!(
+            r#"{{
+            "islands": [
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [] }}
+            ],
+            "computers": [
+                {{ "island_id": 0, "code": "N1 ADDD", "x": 0, "y": 0, "resources": 10 }},
+                {{ "island_id": 0, "filename": {filename:?},
+                   "x": 1, "y": 0, "resources": 10 }}
+            ],
+            "seeds": [
+                {{ "islands": [0], "code": "N1 N2 NOPE", "resources": 10,
+                   "pattern": {{ "type": "scatter", "amount": 10 }} }}
+            ]
+        }}"#,
+            config = config(),
+            filename = filename.to_str().unwrap(),
+        ));
+        let errors = topology.validate().unwrap_err().0;
+        fs::remove_file(&filename).unwrap();
+        assert_eq!(
+            errors,
+            vec![
+                error("computers[0].code", "unknown instructions: ADDD".into()),
+                error("computers[1].filename", "unknown instructions: FOO".into()),
+                error("seeds[0].code", "unknown instructions: NOPE".into()),
+            ]
+        );
+        assert!(topology.computers[0]
+            .program
+            .computer(&Assembler::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_bad_seeds() {
+        let topology = topology(&format!(
+            r#"{{
+            "islands": [
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [] }}
+            ],
+            "computers": [],
+            "seeds": [
+                {{ "islands": [0], "code": "N1", "resources": 10,
+                   "pattern": {{ "type": "scatter", "amount": 10 }},
+                   "immigration_frequency": 1000 }},
+                {{ "islands": [0, 2], "code": "N1", "resources": 10,
+                   "pattern": {{ "type": "grid", "columns": 0, "rows": 2,
+                                 "rect": {{ "x": 5, "y": 5, "w": 6, "h": 5 }} }},
+                   "immigration_frequency": 0 }}
+            ]
+        }}"#,
+            config = config()
+        ));
+        assert_eq!(
+            paths(&topology),
+            vec![
+                "seeds[1].pattern.columns",
+                "seeds[1].pattern.rect",
+                "seeds[1].islands[1]",
+                "seeds[1].immigration_frequency",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bad_extinction() {
+        let topology = topology(&format!(
+            r#"{{
+            "islands": [
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [],
+                   "extinction": {{ "policy": {{ "type": "seed" }}, "check_frequency": 0,
+                                    "pattern": {{ "type": "scatter", "amount": 1 }},
+                                    "resources": 10 }} }},
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [],
+                   "extinction": {{ "policy": {{ "type": "neighbor" }}, "check_frequency": 1,
+                                    "pattern": {{ "type": "scatter", "amount": 1 }},
+                                    "resources": 10 }} }},
+                {{ "config": {config}, "width": 10, "height": 10, "resources": 10,
+                   "connections": [
+                     {{ "from_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_rect": {{ "x": 0, "y": 0, "w": 1, "h": 1 }},
+                        "to_id": 0, "transmit_frequency": 1 }}
+                   ],
+                   "extinction": {{ "policy": {{ "type": "neighbor" }}, "check_frequency": 1,
+                                    "pattern": {{ "type": "scatter", "amount": 1 }},
+                                    "resources": 10 }} }}
+            ],
+            "computers": []
+        }}"#,
+            config = config()
+        ));
+        assert_eq!(
+            paths(&topology),
+            vec![
+                "islands[0].extinction.check_frequency",
+                "islands[0].extinction.policy",
+                "islands[1].extinction.policy",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_single_island() {
+        let island: IslandDescription = serde_json::from_str(&format!(
+            r#"{{ "config": {config}, "width": 11, "height": 6, "resources": 10,
+                  "connections": [] }}"#,
+            config = config()
+        ))
+        .unwrap();
+        let program = ProgramDescription {
+            filename: None,
+            code: Some("N1".to_string()),
+            resources: 10,
+            memory_size: None,
+        };
+        let topology = Topology::single_island(island, program);
+        assert!(topology.validate().is_ok());
+        assert_eq!((topology.computers[0].x, topology.computers[0].y), (5, 3));
+    }
+
+    #[test]
+    fn test_defaults_and_presets() {
+        let json = format!(
+            r#"{{
+            "defaults": {{
+                "config": {config},
+                "width": 10, "height": 10, "resources": 10,
+                "disaster": {{ "frequency": 1000, "width": 2, "height": 2 }},
+                "connections": []
+            }},
+            "presets": {{
+                "fast": {{ "config": {{ "instructions_per_update": 20 }} }},
+                "poor": {{ "resources": 1 }}
+            }},
+            "islands": [
+                {{}},
+                {{ "preset": "fast", "config": {{ "mutation_frequency": 5 }} }},
+                {{ "preset": ["fast", "poor"], "disaster": null, "width": 20 }}
+            ],
+            "computers": []
+        }}"#,
+            config = config()
+        );
+        let topology = Topology::from_reader(json.as_bytes()).unwrap();
+        let islands = &topology.islands;
+        assert_eq!(islands[0].config.instructions_per_update, 10);
+        assert_eq!(islands[0].config.mutation_frequency.0, 10000);
+        assert!(islands[0].disaster.is_some());
+
+        assert_eq!(islands[1].config.instructions_per_update, 20);
+        assert_eq!(islands[1].config.mutation_frequency.0, 5);
+        assert_eq!(islands[1].config.max_processors, 10);
+
+        assert_eq!(islands[2].config.instructions_per_update, 20);
+        assert_eq!(islands[2].resources, 1);
+        assert_eq!(islands[2].width, 20);
+        assert!(islands[2].disaster.is_none());
+    }
+
+    #[test]
+    fn test_unknown_preset() {
+        let json = r#"{ "islands": [ { "preset": "nope" } ], "computers": [] }"#;
+        let error = Topology::from_reader(json.as_bytes()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "islands[0].preset: unknown preset `nope`"
+        );
+    }
 }
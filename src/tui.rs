@@ -0,0 +1,429 @@
+use crate::client_command::{ClientCommand, IslandCommand};
+use crate::fill::{FillScheme, Rgb};
+use crate::info::{FrameDemand, WorldStateInfo};
+use crate::message::Capture;
+#[cfg(unix)]
+use anyhow::anyhow;
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::sync::Arc;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+// a terminal UI, for when there's no browser around. the observed island is
+// drawn with two characters per location, as terminal cells are about twice
+// as high as they are wide. islands too large for the terminal are shown
+// with only one location out of every so many
+
+const CLEAR_SCREEN: &str = "\x1b[2J";
+const CURSOR_HOME: &str = "\x1b[H";
+const CLEAR_TO_END: &str = "\x1b[J";
+const CLEAR_LINE: &str = "\x1b[K";
+const HIDE_CURSOR: &str = "\x1b[?25l";
+const SHOW_CURSOR: &str = "\x1b[?25h";
+const RESET: &str = "\x1b[0m";
+
+// the lines below the island
+const STATUS_LINES: usize = 7;
+// when the terminal doesn't say
+const DEFAULT_SIZE: (usize, usize) = (80, 24);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    NextIsland,
+    PreviousIsland,
+    Island(usize),
+    NextFillScheme,
+    Pause,
+    Quit,
+}
+
+fn parse_key(byte: u8) -> Option<Key> {
+    match byte {
+        b'n' | b'\t' => Some(Key::NextIsland),
+        b'p' => Some(Key::PreviousIsland),
+        b'0'..=b'9' => Some(Key::Island((byte - b'0') as usize)),
+        b'f' => Some(Key::NextFillScheme),
+        b' ' => Some(Key::Pause),
+        b'q' => Some(Key::Quit),
+        _ => None,
+    }
+}
+
+// keys are read one at a time, without echo, until dropped
+#[cfg(unix)]
+struct RawMode {
+    original: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawMode {
+    fn enable() -> Result<RawMode> {
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return Err(anyhow!("The terminal UI needs a terminal"));
+            }
+            let original = termios;
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) != 0 {
+                return Err(anyhow!("Could not set up the terminal"));
+            }
+            Ok(RawMode { original })
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+        print!("{}{}", RESET, SHOW_CURSOR);
+        let _ = std::io::stdout().flush();
+    }
+}
+
+// elsewhere the terminal is left as it is, so keys take enter
+#[cfg(not(unix))]
+struct RawMode;
+
+#[cfg(not(unix))]
+impl RawMode {
+    fn enable() -> Result<RawMode> {
+        Ok(RawMode)
+    }
+}
+
+#[cfg(not(unix))]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        print!("{}{}", RESET, SHOW_CURSOR);
+        let _ = std::io::stdout().flush();
+    }
+}
+
+// columns and rows
+#[cfg(unix)]
+fn terminal_size() -> Option<(usize, usize)> {
+    unsafe {
+        let mut size: libc::winsize = std::mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) != 0 || size.ws_col == 0 {
+            return None;
+        }
+        Some((size.ws_col as usize, size.ws_row as usize))
+    }
+}
+
+#[cfg(not(unix))]
+fn terminal_size() -> Option<(usize, usize)> {
+    None
+}
+
+pub struct Tui {
+    fill_scheme: FillScheme,
+    // of the terminal, in columns and rows
+    size: (usize, usize),
+    observed_island_id: usize,
+    observed_paused: bool,
+    islands_amount: usize,
+    // shown one at a time, and printed once the UI is gone
+    messages: Vec<String>,
+}
+
+impl Tui {
+    pub fn new(fill_scheme: FillScheme) -> Tui {
+        Tui {
+            fill_scheme,
+            size: DEFAULT_SIZE,
+            observed_island_id: 0,
+            observed_paused: false,
+            islands_amount: 1,
+            messages: Vec::new(),
+        }
+    }
+
+    pub fn render(&mut self, info: &WorldStateInfo) -> String {
+        self.observed_island_id = info.observed_island_id;
+        self.islands_amount = info.islands.len();
+        let mut frame = String::new();
+        frame.push_str(CURSOR_HOME);
+        let scale = self.scale(info);
+        let mut last_color = None;
+        for row in info.locations.iter().step_by(scale) {
+            for location in row.iter().step_by(scale) {
+                let color = self.fill_scheme.color(location);
+                if last_color != Some(color) {
+                    let Rgb(r, g, b) = color;
+                    write!(frame, "\x1b[48;2;{};{};{}m", r, g, b).unwrap();
+                    last_color = Some(color);
+                }
+                frame.push_str("  ");
+            }
+            frame.push_str(RESET);
+            frame.push_str(CLEAR_LINE);
+            frame.push_str("\r\n");
+            last_color = None;
+        }
+        let island = &info.islands[info.observed_island_id];
+        self.observed_paused = island.paused;
+        let lines = [
+            format!(
+                "island {} of {} ({}x{}{}){}",
+                info.observed_island_id,
+                info.islands.len(),
+                island.width,
+                island.height,
+                if scale > 1 {
+                    format!(", scaled 1:{}", scale)
+                } else {
+                    String::new()
+                },
+                if island.paused { " paused" } else { "" }
+            ),
+            format!(
+                "ticks {} ({:.0}/s), {:.0} instructions/s",
+                island.ticks, island.ticks_per_second, island.instructions_per_second
+            ),
+            format!(
+                "computers {}, processors {}",
+                island.total_computers, island.total_processors
+            ),
+            format!(
+                "resources free {}, bound {}, memory {}",
+                island.total_free_resources,
+                island.total_bound_resources,
+                island.total_memory_resources
+            ),
+            format!("fill: {}", self.fill_scheme.name()),
+            "n/p/0-9 island, f fill, space pause, q quit".to_string(),
+            self.messages.last().cloned().unwrap_or_default(),
+        ];
+        for line in lines {
+            frame.push_str(&line);
+            frame.push_str(CLEAR_LINE);
+            frame.push_str("\r\n");
+        }
+        frame.push_str(CLEAR_TO_END);
+        frame
+    }
+
+    // how many locations there are to a cell each way, so that the island
+    // fits in the terminal
+    fn scale(&self, info: &WorldStateInfo) -> usize {
+        let (columns, rows) = self.size;
+        let height = info.locations.len();
+        let width = info.locations.first().map_or(0, |row| row.len());
+        let rows = rows.saturating_sub(STATUS_LINES).max(1);
+        let columns = (columns / 2).max(1);
+        width.div_ceil(columns).max(height.div_ceil(rows)).max(1)
+    }
+
+    // what to tell the world when a key is pressed
+    fn handle_key(&mut self, key: Key) -> Option<ClientCommand> {
+        let observe = |island_id| Some(ClientCommand::Observe { island_id });
+        match key {
+            Key::NextIsland => observe((self.observed_island_id + 1) % self.islands_amount),
+            Key::PreviousIsland => {
+                observe((self.observed_island_id + self.islands_amount - 1) % self.islands_amount)
+            }
+            Key::Island(island_id) if island_id < self.islands_amount => observe(island_id),
+            Key::Island(_) => None,
+            Key::NextFillScheme => {
+                self.fill_scheme = self.fill_scheme.next();
+                None
+            }
+            // only the observed island, as shown
+            Key::Pause => {
+                let (respond, _) = oneshot::channel();
+                Some(ClientCommand::ControlIsland {
+                    island_id: self.observed_island_id,
+                    command: if self.observed_paused {
+                        IslandCommand::Resume
+                    } else {
+                        IslandCommand::Pause
+                    },
+                    respond,
+                })
+            }
+            Key::Quit => None,
+        }
+    }
+}
+
+pub async fn tui_task(
+    mut world_state_info_rx: broadcast::Receiver<WorldStateInfo>,
+    client_command_tx: mpsc::Sender<ClientCommand>,
+    fill_scheme: FillScheme,
+    frame_demand: Arc<FrameDemand>,
+) {
+    // held for as long as the terminal UI runs
+    let _full_frames = frame_demand.want_full_frames();
+    let raw_mode = match RawMode::enable() {
+        Ok(raw_mode) => raw_mode,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    let (key_tx, mut key_rx) = mpsc::channel(32);
+    // reading stdin blocks, so it gets a thread of its own
+    std::thread::spawn(move || {
+        let mut stdin = std::io::stdin();
+        let mut buffer = [0; 16];
+        loop {
+            let read = match stdin.read(&mut buffer) {
+                Ok(0) | Err(_) => return,
+                Ok(read) => read,
+            };
+            for key in buffer[..read].iter().filter_map(|&byte| parse_key(byte)) {
+                if key_tx.blocking_send(key).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    let capture = Capture::start();
+    let mut tui = Tui::new(fill_scheme);
+    let mut genotypes = None;
+    let mut last_info = None;
+    print!("{}{}", CLEAR_SCREEN, HIDE_CURSOR);
+    loop {
+        // genotypes are only worked out while they're shown
+        if tui.fill_scheme != FillScheme::Genotype {
+            genotypes = None;
+        } else if genotypes.is_none() {
+            genotypes = Some(frame_demand.want_genotypes());
+        }
+        tokio::select! {
+            info = world_state_info_rx.recv() => {
+                match info {
+                    Ok(info) => last_info = Some(info),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+            key = key_rx.recv() => {
+                let key = match key {
+                    Some(key) => key,
+                    None => break,
+                };
+                if key == Key::Quit {
+                    break;
+                }
+                if let Some(command) = tui.handle_key(key) {
+                    if client_command_tx.send(command).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+        tui.messages.extend(capture.take());
+        tui.size = terminal_size().unwrap_or(DEFAULT_SIZE);
+        if let Some(info) = &last_info {
+            print!("{}", tui.render(info));
+            let _ = std::io::stdout().flush();
+        }
+    }
+    drop(raw_mode);
+    print!("{}{}", CLEAR_SCREEN, CURSOR_HOME);
+    tui.messages.extend(capture.take());
+    drop(capture);
+    for message in &tui.messages {
+        println!("{}", message);
+    }
+    // the world stops, saves if it autosaves, and returns
+    let _ = client_command_tx.send(ClientCommand::Quit).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::habitat::Habitat;
+    use crate::island::Island;
+    use crate::testutil::habitat_config;
+    use crate::world::World;
+
+    fn info() -> WorldStateInfo {
+        let islands = (0..3)
+            .map(|_| {
+                Island::new(
+                    Habitat::new(4, 2, 5),
+                    habitat_config(),
+                    None,
+                    Vec::new(),
+                    None,
+                )
+            })
+            .collect();
+        World::new(islands).info()
+    }
+
+    #[test]
+    fn test_render() {
+        let mut tui = Tui::new(FillScheme::FreeResources);
+        let frame = tui.render(&info());
+        // a row is one color, so it's set once
+        assert_eq!(frame.matches("\x1b[48;2;").count(), 2);
+        assert_eq!(frame.matches("        ").count(), 2);
+        assert!(frame.contains("island 0 of 3 (4x2)"));
+        assert!(frame.contains("fill: free resources"));
+
+        tui.messages.push("Island 1 went extinct".to_string());
+        assert!(tui.render(&info()).contains("Island 1 went extinct"));
+    }
+
+    #[test]
+    fn test_render_scaled() {
+        let mut tui = Tui::new(FillScheme::FreeResources);
+        // room for two locations in a row, and one row
+        tui.size = (4, STATUS_LINES + 1);
+        let frame = tui.render(&info());
+        assert_eq!(frame.matches("\x1b[48;2;").count(), 1);
+        assert_eq!(frame.matches("    ").count(), 1);
+        assert!(!frame.contains("      "));
+        assert!(frame.contains("(4x2, scaled 1:2)"));
+    }
+
+    #[test]
+    fn test_handle_key() {
+        let mut tui = Tui::new(FillScheme::Computers);
+        tui.render(&info());
+        assert!(matches!(
+            tui.handle_key(Key::PreviousIsland),
+            Some(ClientCommand::Observe { island_id: 2 })
+        ));
+        assert!(matches!(
+            tui.handle_key(Key::NextIsland),
+            Some(ClientCommand::Observe { island_id: 1 })
+        ));
+        assert!(tui.handle_key(Key::Island(3)).is_none());
+        assert!(matches!(
+            tui.handle_key(Key::Pause),
+            Some(ClientCommand::ControlIsland {
+                island_id: 0,
+                command: IslandCommand::Pause,
+                ..
+            })
+        ));
+        // whether the island is paused comes from the world
+        let mut info = info();
+        info.islands[0].paused = true;
+        tui.render(&info);
+        assert!(matches!(
+            tui.handle_key(Key::Pause),
+            Some(ClientCommand::ControlIsland {
+                island_id: 0,
+                command: IslandCommand::Resume,
+                ..
+            })
+        ));
+        assert!(tui.handle_key(Key::NextFillScheme).is_none());
+        assert_eq!(tui.fill_scheme, FillScheme::FreeResources);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::habitat::Habitat;
+use crate::info::LocationInfo;
+use crate::rectangle::Rectangle;
+use serde_derive::{Deserialize, Serialize};
+use std::sync::Arc;
+
+// islands can be too large to send every location of. a view has a part of
+// the island at full detail, and an overview of all of it where each cell
+// stands for a square block of locations
+
+const DEFAULT_BLOCK_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    Sum,
+    // per location in the block, including those without a computer
+    #[default]
+    Mean,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewRequest {
+    pub viewport: Rectangle,
+    #[serde(default = "default_block_size")]
+    pub block_size: usize,
+    #[serde(default)]
+    pub aggregate: Aggregate,
+}
+
+fn default_block_size() -> usize {
+    DEFAULT_BLOCK_SIZE
+}
+
+// clients asking for the same overview share it
+pub type OverviewKey = (usize, Aggregate);
+
+impl ViewRequest {
+    pub fn overview_key(&self) -> OverviewKey {
+        (self.block_size.max(1), self.aggregate)
+    }
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewInfo {
+    // the requested viewport, cut off at the edges of the island
+    pub viewport: Rectangle,
+    pub locations: Vec<Vec<LocationInfo>>,
+    pub overview: Arc<OverviewInfo>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OverviewInfo {
+    pub block_size: usize,
+    pub aggregate: Aggregate,
+    // the blocks at the right and bottom edges may be smaller
+    pub blocks: Vec<Vec<BlockInfo>>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockInfo {
+    pub free_resources: f64,
+    pub bound_resources: f64,
+    pub computers: f64,
+    pub memory_size: f64,
+    pub processors: f64,
+}
+
+impl ViewInfo {
+    // the overview is worked out unless it's given
+    pub fn new(
+        habitat: &Habitat,
+        request: &ViewRequest,
+        overview: Option<Arc<OverviewInfo>>,
+    ) -> ViewInfo {
+        let viewport = request.viewport.clamp(habitat.width, habitat.height);
+        let locations = viewport
+            .rows()
+            .map(|y| {
+                viewport
+                    .columns()
+                    .map(|x| habitat.get((x, y)).into())
+                    .collect()
+            })
+            .collect();
+        ViewInfo {
+            viewport,
+            locations,
+            overview: overview
+                .unwrap_or_else(|| Arc::new(OverviewInfo::new(habitat, request.overview_key()))),
+        }
+    }
+}
+
+impl OverviewInfo {
+    pub fn new(habitat: &Habitat, (block_size, aggregate): OverviewKey) -> OverviewInfo {
+        let columns = habitat.width.div_ceil(block_size);
+        let rows = habitat.height.div_ceil(block_size);
+        let mut blocks = vec![vec![BlockInfo::default(); columns]; rows];
+        let mut sizes = vec![vec![0; columns]; rows];
+        for (y, row) in habitat.rows.iter().enumerate() {
+            for (x, location) in row.iter().enumerate() {
+                let block = &mut blocks[y / block_size][x / block_size];
+                sizes[y / block_size][x / block_size] += 1;
+                block.free_resources += location.resources as f64;
+                if let Some(computer) = &location.computer {
+                    block.bound_resources += computer.resources as f64;
+                    block.computers += 1.0;
+                    block.memory_size += computer.memory.values.len() as f64;
+                    block.processors += computer.processors.len() as f64;
+                }
+            }
+        }
+        if aggregate == Aggregate::Mean {
+            for (row, row_sizes) in blocks.iter_mut().zip(&sizes) {
+                for (block, &size) in row.iter_mut().zip(row_sizes) {
+                    let size = size as f64;
+                    block.free_resources /= size;
+                    block.bound_resources /= size;
+                    block.computers /= size;
+                    block.memory_size /= size;
+                    block.processors /= size;
+                }
+            }
+        }
+        OverviewInfo {
+            block_size,
+            aggregate,
+            blocks,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::Computer;
+    use crate::info::FrameDemand;
+
+    fn request(viewport: Rectangle, block_size: usize, aggregate: Aggregate) -> ViewRequest {
+        ViewRequest {
+            viewport,
+            block_size,
+            aggregate,
+        }
+    }
+
+    #[test]
+    fn test_view() {
+        let mut habitat = Habitat::new(5, 3, 10);
+        habitat.set((0, 0), Computer::new(8, 4));
+        habitat.set((4, 2), Computer::new(2, 0));
+
+        let view = ViewInfo::new(
+            &habitat,
+            &request(Rectangle::new(3, 1, 10, 10), 2, Aggregate::Sum),
+            None,
+        );
+        // cut off at the edges
+        assert_eq!(view.locations.len(), 2);
+        assert_eq!(view.locations[0].len(), 2);
+        assert!(view.locations[1][1].computer.is_some());
+        let blocks = &view.overview.blocks;
+        assert_eq!((blocks.len(), blocks[0].len()), (2, 3));
+        assert_eq!(blocks[0][0].free_resources, 40.0);
+        assert_eq!(blocks[0][0].computers, 1.0);
+        assert_eq!(blocks[0][0].memory_size, 8.0);
+        assert_eq!(blocks[1][2].free_resources, 10.0);
+
+        let view = ViewInfo::new(
+            &habitat,
+            &request(Rectangle::new(0, 0, 1, 1), 2, Aggregate::Mean),
+            None,
+        );
+        let blocks = &view.overview.blocks;
+        assert_eq!(blocks[0][0].bound_resources, 1.0);
+        assert_eq!(blocks[0][0].computers, 0.25);
+        // a block of a single location at the corner
+        assert_eq!(blocks[1][2].computers, 1.0);
+        assert_eq!(blocks[1][2].memory_size, 2.0);
+    }
+
+    #[test]
+    fn test_shared_overview() {
+        let frame_demand = Arc::new(FrameDemand::default());
+        let request = request(Rectangle::new(0, 0, 2, 2), 0, Aggregate::Sum);
+        let first = frame_demand.want_overview(request.overview_key());
+        let second = frame_demand.want_overview(request.overview_key());
+        assert_eq!(frame_demand.wanted_overviews(), vec![(1, Aggregate::Sum)]);
+        drop(first);
+        assert_eq!(frame_demand.wanted_overviews().len(), 1);
+        drop(second);
+        assert!(frame_demand.wanted_overviews().is_empty());
+
+        let habitat = Habitat::new(5, 3, 10);
+        let overview = Arc::new(OverviewInfo::new(&habitat, request.overview_key()));
+        let view = ViewInfo::new(&habitat, &request, Some(Arc::clone(&overview)));
+        assert!(Arc::ptr_eq(&view.overview, &overview));
+    }
+}
@@ -62,7 +62,7 @@ impl<T: Eq + Copy + Default> Counts<T> {
         self.pointer = 0;
     }
 
-    fn get_strength(&self) -> impl Iterator<Item = (T, i32)> + '_ {
+    pub fn get_strength(&self) -> impl Iterator<Item = (T, i32)> + '_ {
         self.wants[0..self.pointer].iter().filter_map(|entry| {
             let strength = entry.count - self.cancel;
             if strength > 0 {
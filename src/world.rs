@@ -1,31 +1,83 @@
-use crate::assembler::{text_to_words, Assembler};
-use crate::client_command::ClientCommand;
+use crate::assembler::Assembler;
+use crate::client_command::{ClientCommand, IslandCommand, TopologyEdit};
 use crate::computer::Computer;
 use crate::config::RunConfig;
-use crate::habitat::Habitat;
-use crate::info::WorldStateInfo;
-use crate::island::{Connection, Island};
+use crate::events::{Event, EventBus, EventKind, EventLog};
+use crate::follow::{FollowMessage, Followers, SplitChoice};
+use crate::frames::FrameExporter;
+use crate::habitat::{Coords, Habitat};
+use crate::history::{HistoryConfig, HistoryInfo};
+use crate::info::{FrameDemand, WorldStateInfo};
+use crate::island::{
+    Connection, ExtinctionPolicy, Immigration, Island, IslandSettings, MigrationMode,
+};
+use crate::message::message;
+use crate::metrics::Metrics;
+use crate::migration::{MigrationEvent, MigrationLog};
+use crate::observer::{IslandObserver, ObserverFactories, ObserverFactory};
+use crate::overrides::{apply_overrides, Override};
 use crate::rectangle::Rectangle;
 use crate::serve::serve_task;
 use crate::ticks::Ticks;
-use crate::topology::Topology;
-use anyhow::Result;
+use crate::topology::{IslandDescription, Topology, ValidationErrors};
+use crate::tui::tui_task;
+use crate::view::{OverviewInfo, OverviewKey, ViewInfo, ViewRequest};
+use anyhow::{anyhow, Result};
 use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
 use rand::SeedableRng;
 use serde_derive::{Deserialize, Serialize};
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::io::{BufReader, BufWriter};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant, SystemTime};
 use tokio::sync::{broadcast, mpsc};
 use tokio::time;
 
-const PAUSE_CHECK: Ticks = Ticks(100000);
+// how long a paused island waits before checking again
+const PAUSE_SLEEP: Duration = Duration::from_millis(10);
+// how often island metrics are updated
+const METRICS_INTERVAL: Duration = Duration::from_secs(1);
+// a speed limited island doesn't bother to sleep for less than this
+const SPEED_LIMIT_SLEEP: Duration = Duration::from_millis(5);
+// the slowest an island can be limited to, in ticks per second
+const MIN_SPEED_LIMIT: f64 = 0.1;
+const NEIGHBOR_SAMPLING_TRIES: u64 = 2u64.pow(10);
+// how often connections driven by ticks check the source island
+const CONNECTION_TICKS_CHECK: Duration = Duration::from_millis(10);
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WorldState {
     islands: Vec<Arc<Mutex<Island>>>,
     observed_island: usize,
+    #[serde(default)]
+    migrations: MigrationLog,
+    #[serde(default)]
+    history: HistoryConfig,
+    #[serde(skip)]
+    autosave: AutosaveStatus,
+    #[serde(skip)]
+    events: Arc<EventBus>,
+    #[serde(skip)]
+    observers: ObserverFactories,
+    #[serde(skip)]
+    followers: Arc<Followers>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AutosaveStatus {
+    pub enabled: bool,
+    pub saves: u64,
+    // autosave stops after a failure
+    pub failed: bool,
+    pub last_save: Option<SystemTime>,
+}
+
+// what overrides can change in a loaded world
+#[derive(Debug, Serialize, Deserialize)]
+struct WorldSettings {
+    islands: Vec<IslandSettings>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,29 +85,257 @@ pub struct World {
     world_state: Arc<Mutex<WorldState>>,
 }
 
+// pauses all islands at once
 pub struct LoopControl {
-    paused: bool,
+    paused: AtomicBool,
+    quit: AtomicBool,
+}
+
+// measures how fast an island runs
+struct MetricsSampler {
+    start: Instant,
+    ticks: Ticks,
+    instructions: u64,
+    mutex_wait: Duration,
+}
+
+impl MetricsSampler {
+    fn new(island: &Island) -> MetricsSampler {
+        MetricsSampler {
+            start: Instant::now(),
+            ticks: island.ticks,
+            instructions: island.metrics.instructions,
+            mutex_wait: Duration::ZERO,
+        }
+    }
+
+    fn waited(&mut self, duration: Duration) {
+        self.mutex_wait += duration;
+    }
+
+    fn is_due(&self) -> bool {
+        self.start.elapsed() >= METRICS_INTERVAL
+    }
+
+    fn sample(&mut self, island: &mut Island) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let ticks = island.ticks.0.wrapping_sub(self.ticks.0);
+        let instructions = island.metrics.instructions - self.instructions;
+        island.metrics.ticks_per_second = ticks as f64 / elapsed;
+        island.metrics.instructions_per_second = instructions as f64 / elapsed;
+        island.metrics.mutex_wait = self.mutex_wait.as_secs_f64() / elapsed;
+        self.start = Instant::now();
+        self.ticks = island.ticks;
+        self.instructions = island.metrics.instructions;
+        self.mutex_wait = Duration::ZERO;
+    }
+}
+
+// keeps an island at its speed limit
+struct SpeedLimiter {
+    ticks_per_second: Option<f64>,
+    start: Instant,
+    ticks: u64,
+}
+
+impl SpeedLimiter {
+    fn new() -> SpeedLimiter {
+        SpeedLimiter {
+            ticks_per_second: None,
+            start: Instant::now(),
+            ticks: 0,
+        }
+    }
+
+    // start counting from now
+    fn reset(&mut self) {
+        self.start = Instant::now();
+        self.ticks = 0;
+    }
+
+    fn tick(&mut self, ticks_per_second: Option<f64>) {
+        if ticks_per_second != self.ticks_per_second {
+            self.ticks_per_second = ticks_per_second;
+            self.reset();
+        }
+        let ticks_per_second = match ticks_per_second {
+            Some(ticks_per_second) => ticks_per_second,
+            None => return,
+        };
+        self.ticks += 1;
+        let expected = match Duration::try_from_secs_f64(self.ticks as f64 / ticks_per_second) {
+            Ok(expected) => expected,
+            // not a speed we can keep to
+            Err(_) => return,
+        };
+        let elapsed = self.start.elapsed();
+        if expected > elapsed + SPEED_LIMIT_SLEEP {
+            std::thread::sleep(expected - elapsed);
+        }
+    }
+}
+
+type IslandThreads = Arc<Mutex<Vec<std::thread::JoinHandle<()>>>>;
+
+// what a task that depends on the topology does
+#[derive(Debug, Clone, PartialEq)]
+enum TopologyTaskKind {
+    Connection {
+        from_island_id: usize,
+        connection_id: usize,
+        connection: Box<Connection>,
+    },
+    Extinction {
+        island_id: usize,
+        check_frequency: Duration,
+    },
+}
+
+struct TopologyTask {
+    kind: TopologyTaskKind,
+    // set while the world is locked, so a task that was waiting for the lock
+    // stops before it uses island ids that changed meaning
+    stopped: Arc<AtomicBool>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+// the tasks that depend on the topology. when it's edited, only the tasks
+// that changed are replaced; the others keep their timing
+#[derive(Default)]
+struct TopologyTasks {
+    tasks: Vec<TopologyTask>,
+}
+
+impl TopologyTasks {
+    // call this with the world locked, right after the edit
+    fn update(&mut self, world: &WorldState, world_state: &Arc<Mutex<WorldState>>) {
+        let mut kinds = world.topology_task_kinds();
+        self.tasks.retain(
+            |task| match kinds.iter().position(|kind| *kind == task.kind) {
+                Some(index) => {
+                    kinds.remove(index);
+                    true
+                }
+                None => {
+                    task.stopped.store(true, Ordering::Relaxed);
+                    task.handle.abort();
+                    false
+                }
+            },
+        );
+        for kind in kinds {
+            let stopped = Arc::new(AtomicBool::new(false));
+            let handle = match kind.clone() {
+                TopologyTaskKind::Connection {
+                    from_island_id,
+                    connection_id,
+                    connection,
+                } => tokio::spawn(World::connection_task(
+                    Arc::clone(world_state),
+                    Arc::clone(&stopped),
+                    from_island_id,
+                    connection_id,
+                    *connection,
+                )),
+                TopologyTaskKind::Extinction {
+                    island_id,
+                    check_frequency,
+                } => tokio::spawn(World::extinction_task(
+                    Arc::clone(world_state),
+                    Arc::clone(&stopped),
+                    island_id,
+                    check_frequency,
+                )),
+            };
+            self.tasks.push(TopologyTask {
+                kind,
+                stopped,
+                handle,
+            });
+        }
+    }
 }
 
 impl World {
     pub fn new(islands: Vec<Island>) -> World {
-        World {
+        let world = World {
             world_state: Arc::new(Mutex::new(WorldState {
                 islands: islands
                     .into_iter()
                     .map(|island| Arc::new(Mutex::new(island)))
                     .collect(),
                 observed_island: 0,
+                migrations: MigrationLog::default(),
+                history: HistoryConfig::default(),
+                autosave: AutosaveStatus::default(),
+                events: Arc::new(EventBus::default()),
+                observers: ObserverFactories::default(),
+                followers: Arc::new(Followers::default()),
             })),
-        }
+        };
+        world.world_state.lock().unwrap().number_islands();
+        world
     }
 
     pub fn from_world_state(world_state: WorldState) -> World {
+        world_state.number_islands();
         World {
             world_state: Arc::new(Mutex::new(world_state)),
         }
     }
 
+    // the factory makes an observer for every island, see IslandObserver
+    pub fn add_observer(
+        &self,
+        factory: impl Fn(&Island) -> Box<dyn IslandObserver> + Send + Sync + 'static,
+    ) {
+        self.world_state
+            .lock()
+            .unwrap()
+            .add_observer(Box::new(factory));
+    }
+
+    // the world can also be run by hand, without threads or tokio
+    pub fn step(&self, rng: &mut SmallRng) {
+        self.world_state.lock().unwrap().step(rng);
+    }
+
+    pub fn step_island(&self, island_id: usize, rng: &mut SmallRng) -> Result<()> {
+        let mut world_state = self.world_state.lock().unwrap();
+        world_state.get_island(island_id).map_err(|e| anyhow!(e))?;
+        world_state.step_island(island_id, rng);
+        Ok(())
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, WorldState> {
+        self.world_state.lock().unwrap()
+    }
+
+    pub fn island(&self, island_id: usize) -> Option<Arc<Mutex<Island>>> {
+        self.world_state
+            .lock()
+            .unwrap()
+            .islands
+            .get(island_id)
+            .map(Arc::clone)
+    }
+
+    pub fn islands_amount(&self) -> usize {
+        self.world_state.lock().unwrap().islands.len()
+    }
+
+    pub fn info(&self) -> WorldStateInfo {
+        (&*self.world_state.lock().unwrap()).into()
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        (&*self.world_state.lock().unwrap()).into()
+    }
+
+    pub fn save(&self, filename: &str) -> Result<()> {
+        self.world_state.lock().unwrap().save_dump(filename)
+    }
+
     pub fn set_observed(&mut self, island_id: usize) {
         self.world_state.lock().unwrap().set_observed(island_id);
     }
@@ -66,103 +346,170 @@ impl World {
 
     pub fn run(&self, run_config: RunConfig, assembler: Assembler) -> Result<()> {
         let (habitat_info_tx, _) = broadcast::channel(32);
+        let frame_demand = Arc::new(FrameDemand::default());
         let (client_command_tx, client_command_rx) = mpsc::channel(32);
 
-        let loop_control = Arc::new(Mutex::new(LoopControl { paused: false }));
+        let loop_control = Arc::new(LoopControl {
+            paused: AtomicBool::new(false),
+            quit: AtomicBool::new(false),
+        });
+
+        if let Some(filename) = &run_config.migration_log {
+            self.world_state.lock().unwrap().migrations.open(filename)?;
+        }
+        if let Some(filename) = &run_config.event_log {
+            let event_log = EventLog::create(filename, run_config.event_types.clone())?;
+            self.world_state.lock().unwrap().add_observer(event_log);
+        }
+
+        if run_config.history_ticks.is_some() || run_config.history_size.is_some() {
+            self.world_state
+                .lock()
+                .unwrap()
+                .configure_history(run_config.history_ticks, run_config.history_size);
+        }
+
+        if let Some(frame_config) = &run_config.frames {
+            let exporter = FrameExporter::new(frame_config.clone())?;
+            self.add_observer(move |_island| Box::new(exporter.clone()));
+        }
+
+        if let Some(fill_scheme) = run_config.tui {
+            tokio::spawn(tui_task(
+                habitat_info_tx.subscribe(),
+                client_command_tx.clone(),
+                fill_scheme,
+                Arc::clone(&frame_demand),
+            ));
+        }
 
         if run_config.server {
-            tokio::spawn(serve_task(habitat_info_tx.clone(), client_command_tx));
+            let events = Arc::clone(&self.world_state.lock().unwrap().events);
+            tokio::spawn(serve_task(
+                habitat_info_tx.clone(),
+                client_command_tx,
+                events,
+                Arc::clone(&frame_demand),
+            ));
         }
 
         tokio::spawn(Self::render_world_task(
             Arc::clone(&self.world_state),
             habitat_info_tx,
             run_config.redraw_frequency,
+            frame_demand,
         ));
 
+        let island_threads = Arc::new(Mutex::new(
+            self.spawn_island_tasks(Arc::clone(&loop_control)),
+        ));
+
+        let mut topology_tasks = TopologyTasks::default();
+        topology_tasks.update(&self.world_state.lock().unwrap(), &self.world_state);
+
         tokio::spawn(Self::client_command_task(
             Arc::clone(&self.world_state),
-            Arc::clone(&loop_control),
+            loop_control,
+            topology_tasks,
+            Arc::clone(&island_threads),
             assembler,
             client_command_rx,
         ));
 
         if run_config.autosave.enabled {
+            self.world_state.lock().unwrap().autosave.enabled = true;
             tokio::spawn(Self::save_world_task(
                 Arc::clone(&self.world_state),
                 run_config.autosave.frequency,
             ));
         }
 
-        self.spawn_connection_tasks();
+        // islands can be added while running, so we keep going until no
+        // threads are left
+        loop {
+            let handle = island_threads.lock().unwrap().pop();
+            match handle {
+                Some(handle) => handle.join().unwrap(),
+                None => break,
+            }
+        }
 
-        let handles = self.spawn_island_tasks(loop_control);
-        for handle in handles {
-            handle.join().unwrap();
+        // a last save, so nothing since the previous one is lost
+        let mut world_state = self.world_state.lock().unwrap();
+        if world_state.autosave.enabled && !world_state.autosave.failed {
+            let save_nr = world_state.autosave.saves;
+            if world_state.autosave(save_nr).is_err() {
+                message!("Could not write save file");
+            }
         }
         Ok(())
     }
 
     pub fn spawn_island_tasks(
         &self,
-        loop_control: Arc<Mutex<LoopControl>>,
+        loop_control: Arc<LoopControl>,
     ) -> Vec<std::thread::JoinHandle<()>> {
         let mut handles = Vec::new();
-        for island in &self.world_state.lock().unwrap().islands {
+        let world_state = self.world_state.lock().unwrap();
+        for island in &world_state.islands {
             let island = Arc::clone(island);
             let loop_control = Arc::clone(&loop_control);
-            let handle = std::thread::spawn(move || WorldState::island_task(island, loop_control));
+            let events = Arc::clone(&world_state.events);
+            let followers = Arc::clone(&world_state.followers);
+            let handle = std::thread::spawn(move || {
+                WorldState::island_task(island, loop_control, events, followers)
+            });
             handles.push(handle);
         }
         handles
     }
 
-    fn spawn_connection_tasks(&self) -> Vec<tokio::task::JoinHandle<()>> {
-        let mut handles = Vec::new();
-        let islands_amount = self.world_state.lock().unwrap().islands.len();
-        for from_island_id in 0..islands_amount {
-            let connections = self
-                .world_state
-                .lock()
-                .unwrap()
-                .get_connections(from_island_id);
-            for connection in connections {
-                let world_state = Arc::clone(&self.world_state);
-                handles.push(tokio::spawn(Self::connection_task(
-                    world_state,
-                    from_island_id,
-                    connection.from_rect.clone(),
-                    connection.to_id,
-                    connection.to_rect.clone(),
-                    connection.transmit_frequency,
-                )));
-            }
-        }
-        handles
-    }
-
     async fn render_world_task(
         world_state: Arc<Mutex<WorldState>>,
         tx: broadcast::Sender<WorldStateInfo>,
         duration: Duration,
+        frame_demand: Arc<FrameDemand>,
     ) {
         loop {
-            let _ = tx.send(Self::into_world_state_info(Arc::clone(&world_state)));
+            let _ = tx.send(Self::into_world_state_info(
+                Arc::clone(&world_state),
+                frame_demand.wants_full_frames(),
+                frame_demand.wants_genotypes(),
+                &frame_demand.wanted_overviews(),
+            ));
             time::sleep(duration).await;
         }
     }
 
-    fn into_world_state_info(world_state: Arc<Mutex<WorldState>>) -> WorldStateInfo {
+    fn into_world_state_info(
+        world_state: Arc<Mutex<WorldState>>,
+        full_frame: bool,
+        with_genotypes: bool,
+        overviews: &[OverviewKey],
+    ) -> WorldStateInfo {
         let world_state = world_state.lock().unwrap();
-        (&*world_state).into()
+        let mut info = if full_frame {
+            WorldStateInfo::new(&world_state, with_genotypes)
+        } else {
+            WorldStateInfo::without_locations(&world_state)
+        };
+        if !overviews.is_empty() {
+            let observed = world_state.get_observed();
+            let island = observed.lock().unwrap();
+            info.overviews = overviews
+                .iter()
+                .map(|&key| Arc::new(OverviewInfo::new(&island.habitat, key)))
+                .collect();
+        }
+        info
     }
 
     async fn save_world_task(world_state: Arc<Mutex<WorldState>>, duration: Duration) {
         let mut save_nr = 0;
         loop {
-            let result = world_state.lock().unwrap().save_world(save_nr);
+            let result = world_state.lock().unwrap().autosave(save_nr);
             if result.is_err() {
-                println!("Could not write save file");
+                message!("Could not write save file");
                 break;
             }
             save_nr += 1;
@@ -172,17 +519,24 @@ impl World {
 
     async fn client_command_task(
         world_state: Arc<Mutex<WorldState>>,
-        loop_control: Arc<Mutex<LoopControl>>,
+        loop_control: Arc<LoopControl>,
+        mut topology_tasks: TopologyTasks,
+        island_threads: IslandThreads,
         assembler: Assembler,
         mut rx: mpsc::Receiver<ClientCommand>,
     ) -> Result<()> {
+        // whoever asked may have stopped waiting for the answer, such as a
+        // timed out request or the terminal UI, which is fine
         while let Some(cmd) = rx.recv().await {
             match cmd {
                 ClientCommand::Stop => {
-                    loop_control.lock().unwrap().paused = true;
+                    loop_control.paused.store(true, Ordering::Relaxed);
                 }
                 ClientCommand::Start => {
-                    loop_control.lock().unwrap().paused = false;
+                    loop_control.paused.store(false, Ordering::Relaxed);
+                }
+                ClientCommand::Quit => {
+                    loop_control.quit.store(true, Ordering::Relaxed);
                 }
                 ClientCommand::Observe { island_id } => {
                     world_state.lock().unwrap().set_observed(island_id);
@@ -190,33 +544,149 @@ impl World {
                 ClientCommand::Disassemble { x, y, respond } => {
                     let world = world_state.lock().unwrap();
                     let island = world.islands[world.observed_island].lock().unwrap();
-                    respond
-                        .send(island.habitat.disassemble(&assembler, x, y))
-                        .unwrap();
+                    let _ = respond.send(island.habitat.disassemble(&assembler, x, y));
+                }
+                ClientCommand::Follow {
+                    island_id,
+                    x,
+                    y,
+                    split,
+                    respond,
+                } => {
+                    let world = world_state.lock().unwrap();
+                    let _ = respond.send(world.follow(island_id, (x, y), split));
+                }
+                ClientCommand::View {
+                    request,
+                    overview,
+                    respond,
+                } => {
+                    let world = world_state.lock().unwrap();
+                    let _ = respond.send(world.view(&request, overview));
+                }
+                ClientCommand::History {
+                    island_id,
+                    from,
+                    to,
+                    max_samples,
+                    respond,
+                } => {
+                    let world = world_state.lock().unwrap();
+                    let _ = respond.send(world.history(island_id, from, to, max_samples));
+                }
+                ClientCommand::Metrics { respond } => {
+                    let world = world_state.lock().unwrap();
+                    let _ = respond.send(Metrics::from(&*world));
+                }
+                ClientCommand::Migrations { limit, respond } => {
+                    let world = world_state.lock().unwrap();
+                    let _ = respond.send(world.migrations.info(limit));
+                }
+                ClientCommand::ControlIsland {
+                    island_id,
+                    command,
+                    respond,
+                } => {
+                    let result = world_state
+                        .lock()
+                        .unwrap()
+                        .control_island(island_id, command);
+                    let _ = respond.send(result);
+                }
+                ClientCommand::EditTopology { edit, respond } => {
+                    let adds_island = matches!(
+                        *edit,
+                        TopologyEdit::AddIsland { .. } | TopologyEdit::CloneIsland { .. }
+                    );
+                    // the tasks are updated before the lock is released, so
+                    // none of them sees the edited world with old ids
+                    let mut world = world_state.lock().unwrap();
+                    let result = world.edit_topology(*edit);
+                    if let Ok(id) = result {
+                        topology_tasks.update(&world, &world_state);
+                        // a new island needs a thread of its own
+                        if adds_island {
+                            let island = Arc::clone(&world.islands[id]);
+                            let events = Arc::clone(&world.events);
+                            let followers = Arc::clone(&world.followers);
+                            let loop_control = Arc::clone(&loop_control);
+                            island_threads
+                                .lock()
+                                .unwrap()
+                                .push(std::thread::spawn(move || {
+                                    WorldState::island_task(island, loop_control, events, followers)
+                                }));
+                        }
+                    }
+                    drop(world);
+                    let _ = respond.send(result);
                 }
             }
         }
         Ok(())
     }
 
-    async fn connection_task(
+    async fn extinction_task(
         world_state: Arc<Mutex<WorldState>>,
-        from_island_id: usize,
-        from_rect: Rectangle,
-        to_island_id: usize,
-        to_rect: Rectangle,
+        stopped: Arc<AtomicBool>,
+        island_id: usize,
         duration: Duration,
     ) {
         let mut rng = SmallRng::from_entropy();
         loop {
-            world_state.lock().unwrap().transfer(
-                from_island_id,
-                &from_rect,
-                to_island_id,
-                &to_rect,
-                &mut rng,
-            );
             time::sleep(duration).await;
+            let mut world_state = world_state.lock().unwrap();
+            if stopped.load(Ordering::Relaxed) {
+                return;
+            }
+            world_state.check_extinction(island_id, &mut rng);
+        }
+    }
+
+    async fn connection_task(
+        world_state: Arc<Mutex<WorldState>>,
+        stopped: Arc<AtomicBool>,
+        from_island_id: usize,
+        connection_id: usize,
+        connection: Connection,
+    ) {
+        let mut rng = SmallRng::from_entropy();
+        match (connection.transmit_ticks, connection.transmit_frequency) {
+            (Some(frequency), _) => {
+                // we follow the ticks of the source island, so that migration
+                // doesn't depend on how fast the machine is
+                let mut last = {
+                    let world_state = world_state.lock().unwrap();
+                    if stopped.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    world_state.get_ticks(from_island_id)
+                };
+                loop {
+                    time::sleep(CONNECTION_TICKS_CHECK).await;
+                    let mut world_state = world_state.lock().unwrap();
+                    if stopped.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let ticks = world_state.get_ticks(from_island_id);
+                    let events = ticks.0.wrapping_sub(last.0) / frequency.0;
+                    last = Ticks(last.0.wrapping_add(events * frequency.0));
+                    for _ in 0..events {
+                        world_state.transfer(from_island_id, connection_id, &connection, &mut rng);
+                    }
+                }
+            }
+            (None, Some(duration)) => loop {
+                {
+                    let mut world_state = world_state.lock().unwrap();
+                    if stopped.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    world_state.transfer(from_island_id, connection_id, &connection, &mut rng);
+                }
+                time::sleep(duration).await;
+            },
+            (None, None) => {}
         }
     }
 }
@@ -224,36 +694,184 @@ impl World {
 impl WorldState {
     // this is the only task that isn't async but runs in a thread to make use of
     // multiple cores
-    fn island_task(island: Arc<Mutex<Island>>, loop_control: Arc<Mutex<LoopControl>>) {
-        let mut ticks = Ticks(0);
+    fn island_task(
+        island: Arc<Mutex<Island>>,
+        loop_control: Arc<LoopControl>,
+        events: Arc<EventBus>,
+        followers: Arc<Followers>,
+    ) {
         let mut rng = SmallRng::from_entropy();
+        let mut speed_limiter = SpeedLimiter::new();
+        let mut metrics_sampler = MetricsSampler::new(&island.lock().unwrap());
         loop {
-            island.lock().unwrap().update(ticks, &mut rng);
+            if loop_control.quit.load(Ordering::Relaxed) {
+                return;
+            }
+            // pausing is checked every tick so that it takes effect right away
+            let (speed_limit, island_events) = if loop_control.paused.load(Ordering::Relaxed) {
+                (None, Vec::new())
+            } else {
+                let start = Instant::now();
+                let mut island = island.lock().unwrap();
+                metrics_sampler.waited(start.elapsed());
+                if island.retired {
+                    return;
+                }
+                if island.control.take_tick() {
+                    let island_events =
+                        Self::update_island(&mut island, &mut rng, &events, &followers);
+                    (Some(island.control.speed_limit), island_events)
+                } else {
+                    (None, Vec::new())
+                }
+            };
+            // websocket subscribers may be slow, so they don't get to hold the lock
+            events.publish(&island_events);
+            match speed_limit {
+                Some(speed_limit) => speed_limiter.tick(speed_limit),
+                None => {
+                    std::thread::sleep(PAUSE_SLEEP);
+                    // don't catch up on the time spent paused
+                    speed_limiter.reset();
+                }
+            }
+            if metrics_sampler.is_due() {
+                metrics_sampler.sample(&mut island.lock().unwrap());
+            }
+        }
+    }
+
+    // one tick of the island, returns the events to publish once it's unlocked
+    fn update_island(
+        island: &mut Island,
+        rng: &mut SmallRng,
+        events: &EventBus,
+        followers: &Followers,
+    ) -> Vec<Event> {
+        Self::record_events(island, events, followers);
+        followers.before_update(island);
+        island.update(rng);
+        let island_events = island.take_events();
+        // the observers are taken out so they can look at the island
+        let mut observers = std::mem::take(&mut island.observers);
+        observers.updated(island, &island_events);
+        island.observers = observers;
+        followers.updated(island, &island_events);
+        island_events
+    }
+
+    // only while someone is interested
+    fn record_events(island: &mut Island, events: &EventBus, followers: &Followers) {
+        island.habitat.record_events(
+            events.is_active() || island.observers.is_active() || followers.is_active(),
+        );
+    }
+
+    // events that happened to the island outside of its updates
+    fn island_events(island: &mut Island, events: &[Event]) {
+        if events.is_empty() {
+            return;
+        }
+        let mut observers = std::mem::take(&mut island.observers);
+        observers.events(island, events);
+        island.observers = observers;
+    }
 
-            if ticks.is_at(PAUSE_CHECK) {
-                let control = loop_control.lock().unwrap();
-                if control.paused {
-                    drop(control);
-                    Self::pause(Arc::clone(&loop_control));
+    // one tick of every island, one after the other, followed by the
+    // migrations that are due. only connections with transmit_ticks migrate,
+    // as there's no clock when stepping by hand
+    pub fn step(&mut self, rng: &mut SmallRng) {
+        for island_id in 0..self.islands.len() {
+            self.step_island(island_id, rng);
+        }
+    }
+
+    pub fn step_island(&mut self, island_id: usize, rng: &mut SmallRng) {
+        let (island_events, ticks, connections) = {
+            let mut island = self.islands[island_id].lock().unwrap();
+            let island_events =
+                Self::update_island(&mut island, rng, &self.events, &self.followers);
+            (island_events, island.ticks, island.connections.clone())
+        };
+        self.events.publish(&island_events);
+        for (connection_id, connection) in connections.iter().enumerate() {
+            if let Some(frequency) = connection.transmit_ticks {
+                if ticks.is_at(frequency) {
+                    self.transfer(island_id, connection_id, connection, rng);
                 }
             }
-            ticks = ticks.tick();
         }
     }
 
-    fn pause(loop_control: Arc<Mutex<LoopControl>>) {
-        loop {
-            let control = loop_control.lock().unwrap();
-            if !control.paused {
-                break;
+    pub fn control_island(
+        &mut self,
+        island_id: usize,
+        command: IslandCommand,
+    ) -> Result<(), String> {
+        let mut island = self.get_island(island_id)?.lock().unwrap();
+        match command {
+            IslandCommand::Pause => island.control.paused = true,
+            IslandCommand::Resume => {
+                island.control.paused = false;
+                island.control.steps = 0;
+            }
+            IslandCommand::Step { ticks } => {
+                island.control.paused = true;
+                island.control.steps = ticks;
+            }
+            IslandCommand::SpeedLimit { ticks_per_second } => {
+                if ticks_per_second.is_some_and(|ticks_per_second| {
+                    !ticks_per_second.is_finite() || ticks_per_second < MIN_SPEED_LIMIT
+                }) {
+                    return Err(format!(
+                        "Speed limit must be a number of at least {} ticks per second",
+                        MIN_SPEED_LIMIT
+                    ));
+                }
+                island.control.speed_limit = ticks_per_second;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply_overrides(&mut self, overrides: &[Override]) -> Result<()> {
+        let settings = WorldSettings {
+            islands: self
+                .islands
+                .iter()
+                .map(|island| island.lock().unwrap().settings())
+                .collect(),
+        };
+        let settings = apply_overrides(&settings, overrides)?;
+        let mut topology = Self::topology(&self.islands);
+        for (description, island_settings) in topology.islands.iter_mut().zip(&settings.islands) {
+            description.set_settings(island_settings.clone());
+        }
+        Self::validate_topology(&topology, &self.islands)?;
+        for (island, island_settings) in self.islands.iter().zip(settings.islands) {
+            island.lock().unwrap().set_settings(island_settings);
+        }
+        Ok(())
+    }
+
+    fn autosave(&mut self, save_nr: u64) -> Result<()> {
+        let result = self.save_world(save_nr);
+        match result {
+            Ok(()) => {
+                self.autosave.saves += 1;
+                self.autosave.last_save = Some(SystemTime::now());
             }
-            drop(control);
-            std::thread::sleep(Duration::from_millis(100));
+            Err(_) => self.autosave.failed = true,
         }
+        result
     }
 
     fn save_world(&self, save_nr: u64) -> Result<()> {
-        let file = BufWriter::new(File::create(format!("apilar-dump{:06}.aplr", save_nr))?);
+        self.save_dump(&format!("apilar-dump{:06}.aplr", save_nr))
+    }
+
+    pub fn save_dump(&self, filename: &str) -> Result<()> {
+        let file = BufWriter::new(File::create(filename)?);
 
         let mut zip = zip::ZipWriter::new(file);
 
@@ -263,9 +881,24 @@ impl WorldState {
         Ok(())
     }
 
+    pub fn read_dump(filename: &str) -> Result<WorldState> {
+        let file = BufReader::new(File::open(filename)?);
+
+        let mut archive = zip::ZipArchive::new(file)?;
+
+        let cbor_file = match archive.by_name("data.cbor") {
+            Ok(file) => file,
+            Err(_) => {
+                return Err(anyhow!("data.cbor not found in archive"));
+            }
+        };
+
+        Ok(serde_cbor::from_reader(cbor_file)?)
+    }
+
     fn set_observed(&mut self, island_id: usize) {
         if island_id >= self.islands.len() {
-            println!("Island id {} out of range", island_id);
+            message!("Island id {} out of range", island_id);
             return;
         }
         self.observed_island = island_id;
@@ -275,6 +908,14 @@ impl WorldState {
         Arc::clone(&self.islands[self.observed_island])
     }
 
+    pub fn view(&self, request: &ViewRequest, overview: Option<Arc<OverviewInfo>>) -> ViewInfo {
+        ViewInfo::new(
+            &self.get_observed().lock().unwrap().habitat,
+            request,
+            overview,
+        )
+    }
+
     pub fn get_observed_id(&self) -> usize {
         self.observed_island
     }
@@ -283,37 +924,512 @@ impl WorldState {
         &self.islands
     }
 
+    pub fn get_migrations(&self) -> &MigrationLog {
+        &self.migrations
+    }
+
+    pub fn get_autosave(&self) -> &AutosaveStatus {
+        &self.autosave
+    }
+
+    // returns how many computers were transferred
     fn transfer(
         &mut self,
         from_island_id: usize,
-        from_rect: &Rectangle,
-        to_island_id: usize,
-        to_rect: &Rectangle,
+        connection_id: usize,
+        connection: &Connection,
         rng: &mut SmallRng,
-    ) {
-        let islands = self.get_islands_by_id(&[from_island_id, to_island_id]);
-        let from_island = &islands[0];
-        let to_island = &islands[1];
-        let transfer = from_island.lock().unwrap().habitat.get_connection_transfer(
+    ) -> usize {
+        let mut events = Vec::new();
+        for _ in 0..connection.amount {
+            if let Some((event, computer_id, migrant_id)) =
+                self.transfer_one(from_island_id, connection_id, connection, rng)
+            {
+                events.push(Event {
+                    island_id: from_island_id,
+                    ticks: event.ticks,
+                    kind: EventKind::Migration {
+                        from_coords: event.from_coords,
+                        computer_id,
+                        to_island_id: event.to_island_id,
+                        to_coords: event.to_coords,
+                        migrant_id,
+                        genotype: event.genotype,
+                    },
+                });
+                self.migrations.record(event);
+            }
+        }
+        if connection.mode == MigrationMode::Move {
+            self.followers.migrated(&events);
+        }
+        let migrated = events.len();
+        Self::island_events(&mut self.islands[from_island_id].lock().unwrap(), &events);
+        // the migrants arrive on the other island
+        if migrated > 0 {
+            let mut to_island = self.islands[connection.to_id].lock().unwrap();
+            let arrivals = to_island.take_placed_events();
+            Self::island_events(&mut to_island, &arrivals);
+            events.extend(arrivals);
+        }
+        self.events.publish(&events);
+        migrated
+    }
+
+    fn transfer_one(
+        &self,
+        from_island_id: usize,
+        connection_id: usize,
+        connection: &Connection,
+        rng: &mut SmallRng,
+    ) -> Option<(MigrationEvent, u64, u64)> {
+        let same_island = from_island_id == connection.to_id;
+        let islands = self.get_islands_by_id(&[from_island_id, connection.to_id]);
+        let mut from_island = islands[0].lock().unwrap();
+        // a connection may go back to the island itself, which is already locked
+        let mut to_island = if same_island {
+            None
+        } else {
+            Some(islands[1].lock().unwrap())
+        };
+        Self::record_events(
+            to_island.as_deref_mut().unwrap_or(&mut from_island),
+            &self.events,
+            &self.followers,
+        );
+        let transfer = from_island.habitat.get_connection_transfer(
             rng,
-            from_rect,
-            to_rect,
-            &to_island.lock().unwrap().habitat,
+            &connection.from_rect,
+            &connection.to_rect,
+            match &to_island {
+                Some(to_island) => &to_island.habitat,
+                None => &from_island.habitat,
+            },
+            |computer| {
+                connection
+                    .filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.accepts(computer))
+            },
         );
 
-        if let Some((from_coords, to_coords, computer)) = transfer {
-            from_island
-                .lock()
-                .unwrap()
-                .habitat
-                .get_mut(from_coords)
-                .computer = None;
-            to_island
-                .lock()
-                .unwrap()
-                .habitat
-                .get_mut(to_coords)
-                .computer = Some(computer)
+        if let Some((from_coords, to_coords, mut computer)) = transfer {
+            let event = MigrationEvent {
+                ticks: from_island.ticks,
+                from_island_id,
+                connection_id,
+                from_coords,
+                to_island_id: connection.to_id,
+                to_coords,
+                genotype: computer.genotype(),
+            };
+            if let Some(mutation) = &connection.mutation {
+                mutation.apply(&mut computer, rng);
+            }
+            if connection.mode == MigrationMode::Move {
+                from_island.habitat.get_mut(from_coords).computer = None;
+            }
+            let computer_id = computer.id;
+            let migrant_id = match &mut to_island {
+                Some(to_island) => to_island.habitat.set(to_coords, computer),
+                None => from_island.habitat.set(to_coords, computer),
+            };
+            Some((event, computer_id, migrant_id))
+        } else {
+            None
+        }
+    }
+
+    // reseed an island if it's gone extinct, otherwise remember its dominant
+    // genotype in case it does later
+    fn check_extinction(&mut self, island_id: usize, rng: &mut SmallRng) {
+        let island = Arc::clone(&self.islands[island_id]);
+        let mut island = island.lock().unwrap();
+        let extinction = match &island.extinction {
+            Some(extinction) => extinction.clone(),
+            None => return,
+        };
+        if island.habitat.computers_amount() > 0 {
+            island.update_genebank();
+            return;
+        }
+        let computer = match extinction.policy {
+            ExtinctionPolicy::Neighbor => self.sample_neighbor(island_id, &island.connections, rng),
+            _ => island.reseed_computer(rng),
+        };
+        match computer {
+            Some(computer) => {
+                Self::record_events(&mut island, &self.events, &self.followers);
+                let placed = extinction
+                    .pattern
+                    .place(&mut island.habitat, &computer, rng);
+                let mut events = vec![Event {
+                    island_id,
+                    ticks: island.ticks,
+                    kind: EventKind::Reseed {
+                        policy: extinction.policy.clone(),
+                        computers: placed,
+                    },
+                }];
+                events.extend(island.take_placed_events());
+                Self::island_events(&mut island, &events);
+                self.events.publish(&events);
+                message!(
+                    "Island {} went extinct, reseeded {} computers ({:?})",
+                    island_id,
+                    placed,
+                    extinction.policy
+                );
+            }
+            None => {
+                message!(
+                    "Island {} went extinct, but could not reseed ({:?})",
+                    island_id,
+                    extinction.policy
+                );
+            }
+        }
+    }
+
+    // a copy of a computer on a random island connected to this one
+    fn sample_neighbor(
+        &self,
+        island_id: usize,
+        connections: &[Connection],
+        rng: &mut SmallRng,
+    ) -> Option<Computer> {
+        let mut neighbor_ids = self.get_neighbor_ids(island_id, connections);
+        neighbor_ids.shuffle(rng);
+        for neighbor_id in neighbor_ids {
+            let neighbor = self.islands[neighbor_id].lock().unwrap();
+            let habitat = &neighbor.habitat;
+            let rect = Rectangle::new(0, 0, habitat.width, habitat.height);
+            if let Some((computer, _)) = habitat.take_sample(rng, &rect, NEIGHBOR_SAMPLING_TRIES) {
+                return Some(computer);
+            }
+        }
+        None
+    }
+
+    // islands connected to this one, in either direction. we get the
+    // connections of the island itself passed in as it's already locked
+    fn get_neighbor_ids(&self, island_id: usize, connections: &[Connection]) -> Vec<usize> {
+        let mut neighbor_ids = Vec::new();
+        for (other_id, other) in self.islands.iter().enumerate() {
+            if other_id == island_id {
+                continue;
+            }
+            let connected = connections
+                .iter()
+                .any(|connection| connection.to_id == other_id)
+                || other
+                    .lock()
+                    .unwrap()
+                    .connections
+                    .iter()
+                    .any(|connection| connection.to_id == island_id);
+            if connected {
+                neighbor_ids.push(other_id);
+            }
+        }
+        neighbor_ids
+    }
+
+    // the connection and extinction tasks this topology needs
+    fn topology_task_kinds(&self) -> Vec<TopologyTaskKind> {
+        let mut kinds = Vec::new();
+        for (island_id, island) in self.islands.iter().enumerate() {
+            let island = island.lock().unwrap();
+            for (connection_id, connection) in island.connections.iter().enumerate() {
+                kinds.push(TopologyTaskKind::Connection {
+                    from_island_id: island_id,
+                    connection_id,
+                    connection: Box::new(connection.clone()),
+                });
+            }
+            if let Some(extinction) = &island.extinction {
+                kinds.push(TopologyTaskKind::Extinction {
+                    island_id,
+                    check_frequency: extinction.check_frequency,
+                });
+            }
+        }
+        kinds
+    }
+
+    pub fn get_ticks(&self, island_id: usize) -> Ticks {
+        self.islands[island_id].lock().unwrap().ticks
+    }
+
+    pub fn edit_topology(&mut self, edit: TopologyEdit) -> Result<usize, String> {
+        match edit {
+            TopologyEdit::AddConnection {
+                island_id,
+                connection,
+            } => {
+                let connection_id = self
+                    .get_island(island_id)?
+                    .lock()
+                    .unwrap()
+                    .connections
+                    .len();
+                self.check_edit(
+                    &[format!(
+                        "islands[{}].connections[{}]",
+                        island_id, connection_id
+                    )],
+                    |topology| {
+                        topology.islands[island_id]
+                            .connections
+                            .push(connection.clone())
+                    },
+                )?;
+                self.islands[island_id]
+                    .lock()
+                    .unwrap()
+                    .connections
+                    .push(connection);
+                Ok(connection_id)
+            }
+            TopologyEdit::UpdateConnection {
+                island_id,
+                connection_id,
+                connection,
+            } => {
+                self.get_connection(island_id, connection_id)?;
+                self.check_edit(
+                    &[format!(
+                        "islands[{}].connections[{}]",
+                        island_id, connection_id
+                    )],
+                    |topology| {
+                        topology.islands[island_id].connections[connection_id] = connection.clone()
+                    },
+                )?;
+                self.islands[island_id].lock().unwrap().connections[connection_id] = connection;
+                Ok(connection_id)
+            }
+            TopologyEdit::RemoveConnection {
+                island_id,
+                connection_id,
+            } => {
+                let to_id = self.get_connection(island_id, connection_id)?.to_id;
+                // either end may need the other as a neighbor to reseed from
+                self.check_edit(
+                    &[
+                        format!("islands[{}].connections", island_id),
+                        format!("islands[{}].extinction", island_id),
+                        format!("islands[{}].extinction", to_id),
+                    ],
+                    |topology| {
+                        topology.islands[island_id]
+                            .connections
+                            .remove(connection_id);
+                    },
+                )?;
+                self.islands[island_id]
+                    .lock()
+                    .unwrap()
+                    .connections
+                    .remove(connection_id);
+                Ok(connection_id)
+            }
+            TopologyEdit::AddIsland { island } => {
+                let island_id = self.islands.len();
+                self.check_edit(&[format!("islands[{}]", island_id)], |topology| {
+                    topology.islands.push(island.clone())
+                })?;
+                let mut island = island.island();
+                island.id = island_id;
+                self.configure_island_history(&mut island);
+                self.observers.observe(&mut island);
+                self.islands.push(Arc::new(Mutex::new(island)));
+                self.number_islands();
+                Ok(island_id)
+            }
+            TopologyEdit::CloneIsland { island_id } => {
+                let mut island = self.get_island(island_id)?.lock().unwrap().clone();
+                island.connections.clear();
+                island.take_events();
+                island.id = self.islands.len();
+                // the clone has its own history from here on
+                island.history.clear();
+                self.configure_island_history(&mut island);
+                self.observers.observe(&mut island);
+                self.islands.push(Arc::new(Mutex::new(island)));
+                self.number_islands();
+                Ok(self.islands.len() - 1)
+            }
+            TopologyEdit::RemoveIsland { island_id } => {
+                self.remove_island(island_id)?;
+                self.number_islands();
+                self.followers.island_removed(island_id);
+                Ok(island_id)
+            }
+        }
+    }
+
+    // the observers of every island, now and later
+    pub fn add_observer(&mut self, factory: ObserverFactory) {
+        for island in &self.islands {
+            let mut island = island.lock().unwrap();
+            let observer = factory(&island);
+            island.observers.add(observer);
+        }
+        self.observers.add(factory);
+    }
+
+    // for islands added later too
+    pub fn configure_history(&mut self, frequency: Option<Ticks>, size: Option<usize>) {
+        if frequency.is_some() {
+            self.history.frequency = frequency;
+        }
+        if size.is_some() {
+            self.history.size = size;
+        }
+        for island in &self.islands {
+            self.configure_island_history(&mut island.lock().unwrap());
+        }
+    }
+
+    fn configure_island_history(&self, island: &mut Island) {
+        island
+            .history
+            .configure(self.history.frequency, self.history.size);
+    }
+
+    pub fn history(
+        &self,
+        island_id: usize,
+        from: Option<u64>,
+        to: Option<u64>,
+        max_samples: Option<usize>,
+    ) -> Result<HistoryInfo, String> {
+        let island = self.get_island(island_id)?.lock().unwrap();
+        Ok(HistoryInfo {
+            island_id,
+            frequency: island.history.frequency,
+            samples: island.history.query(from, to, max_samples),
+        })
+    }
+
+    // streams what the computer at the coordinates does every update
+    pub fn follow(
+        &self,
+        island_id: usize,
+        coords: Coords,
+        split: SplitChoice,
+    ) -> Result<mpsc::Receiver<FollowMessage>, String> {
+        let island = self.get_island(island_id)?.lock().unwrap();
+        self.followers.follow(&island, coords, split)
+    }
+
+    pub fn get_island(&self, island_id: usize) -> Result<&Arc<Mutex<Island>>, String> {
+        self.islands
+            .get(island_id)
+            .ok_or_else(|| format!("Island id {} out of range", island_id))
+    }
+
+    fn get_connection(&self, island_id: usize, connection_id: usize) -> Result<Connection, String> {
+        self.get_island(island_id)?
+            .lock()
+            .unwrap()
+            .connections
+            .get(connection_id)
+            .cloned()
+            .ok_or_else(|| {
+                format!(
+                    "Connection id {} out of range for island {}",
+                    connection_id, island_id
+                )
+            })
+    }
+
+    // islands as a topology, to validate changes with the same rules as
+    // configs
+    fn topology(islands: &[Arc<Mutex<Island>>]) -> Topology {
+        Topology {
+            islands: islands
+                .iter()
+                .map(|island| IslandDescription::from(&*island.lock().unwrap()))
+                .collect(),
+            computers: Vec::new(),
+            seeds: Vec::new(),
+        }
+    }
+
+    // the islands keep their own seed computers
+    fn validate_topology(
+        topology: &Topology,
+        islands: &[Arc<Mutex<Island>>],
+    ) -> Result<(), ValidationErrors> {
+        topology.validate_seeded(|island_id| {
+            islands
+                .get(island_id)
+                .is_some_and(|island| !island.lock().unwrap().seeds.is_empty())
+        })
+    }
+
+    // validate an edit, but only complain about what's at the edited path
+    // only errors under the paths count, so problems the world already had
+    // don't get in the way
+    fn check_edit(&self, paths: &[String], edit: impl FnOnce(&mut Topology)) -> Result<(), String> {
+        let mut topology = Self::topology(&self.islands);
+        edit(&mut topology);
+        let errors = match Self::validate_topology(&topology, &self.islands) {
+            Ok(()) => return Ok(()),
+            Err(errors) => errors,
+        };
+        let messages: Vec<String> = errors
+            .0
+            .iter()
+            .filter(|error| paths.iter().any(|path| error.path.starts_with(path)))
+            .map(|error| error.to_string())
+            .collect();
+        if messages.is_empty() {
+            Ok(())
+        } else {
+            Err(messages.join("; "))
+        }
+    }
+
+    // connections to the island are removed, and ids of later islands shift
+    // down by one
+    fn remove_island(&mut self, island_id: usize) -> Result<(), String> {
+        self.get_island(island_id)?;
+        if self.islands.len() == 1 {
+            return Err("Cannot remove the last island".to_string());
+        }
+        // the other islands must make sense without it; an island may need
+        // it as a neighbor to reseed from
+        let mut islands = self.islands.clone();
+        islands.remove(island_id);
+        let mut topology = Self::topology(&islands);
+        for description in &mut topology.islands {
+            remove_connections_to(&mut description.connections, island_id);
+        }
+        if let Err(errors) = Self::validate_topology(&topology, &islands) {
+            let messages: Vec<String> = errors.0.iter().map(|error| error.to_string()).collect();
+            return Err(messages.join("; "));
+        }
+
+        let island = self.islands.remove(island_id);
+        island.lock().unwrap().retired = true;
+        for other in &self.islands {
+            remove_connections_to(&mut other.lock().unwrap().connections, island_id);
+        }
+        if self.observed_island == island_id {
+            self.observed_island = 0;
+        } else if self.observed_island > island_id {
+            self.observed_island -= 1;
+        }
+        Ok(())
+    }
+
+    // islands know their own id, so their events can say where they happened
+    fn number_islands(&self) {
+        for (island_id, island) in self.islands.iter().enumerate() {
+            island.lock().unwrap().id = island_id;
         }
     }
 
@@ -323,11 +1439,16 @@ impl WorldState {
             .map(|id| Arc::clone(&self.islands[*id]))
             .collect()
     }
+}
 
-    fn get_connections(&self, from_island_id: usize) -> Vec<Connection> {
-        let from_island = &self.islands[from_island_id];
-        // XXX clone is a bit of a hack
-        return from_island.lock().unwrap().connections.clone();
+// connections to a removed island go, and those to later islands follow their
+// ids down by one
+fn remove_connections_to(connections: &mut Vec<Connection>, island_id: usize) {
+    connections.retain(|connection| connection.to_id != island_id);
+    for connection in connections {
+        if connection.to_id > island_id {
+            connection.to_id -= 1;
+        }
     }
 }
 
@@ -335,6 +1456,7 @@ impl TryFrom<&Topology> for World {
     type Error = anyhow::Error;
 
     fn try_from(topology: &Topology) -> Result<Self> {
+        topology.validate()?;
         let mut islands = Vec::new();
         for island_description in &topology.islands {
             let habitat = Habitat::new(
@@ -342,26 +1464,590 @@ impl TryFrom<&Topology> for World {
                 island_description.height,
                 island_description.resources,
             );
-            // XXX should verify that connections make sense, both id and dimensions
             islands.push(Island::new(
                 habitat,
                 island_description.config.clone(),
                 island_description.disaster.clone(),
                 island_description.connections.clone(),
+                island_description.extinction.clone(),
             ))
         }
         let assembler = Assembler::new();
         for computer_description in &topology.computers {
-            let habitat = &mut islands[computer_description.island_id].habitat;
-            let mut file = BufReader::new(File::open(computer_description.filename.clone())?);
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
-            let words = text_to_words(&contents);
-            let mut computer = Computer::new(words.len(), computer_description.resources);
-            assembler.assemble_words(words, &mut computer.memory, 0);
-            computer.add_processor(0);
-            habitat.set((computer_description.x, computer_description.y), computer);
+            let island = &mut islands[computer_description.island_id];
+            let computer = computer_description.program.computer(&assembler)?;
+            island.seeds.push(computer.clone());
+            island
+                .habitat
+                .set((computer_description.x, computer_description.y), computer);
+        }
+        let mut rng = SmallRng::from_entropy();
+        for seed_description in &topology.seeds {
+            let computer = seed_description.program.computer(&assembler)?;
+            for island_id in &seed_description.islands {
+                let island = &mut islands[*island_id];
+                island.seeds.push(computer.clone());
+                match seed_description.immigration_frequency {
+                    // immigration happens at tick 0 too, so no need to place it now
+                    Some(frequency) => island.immigrations.push(Immigration {
+                        computer: computer.clone(),
+                        pattern: seed_description.pattern.clone(),
+                        frequency,
+                    }),
+                    None => {
+                        seed_description
+                            .pattern
+                            .place(&mut island.habitat, &computer, &mut rng);
+                    }
+                }
+            }
         }
         Ok(World::new(islands))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventType;
+    use crate::habitat::{Habitat, Mutation};
+    use crate::island::{Extinction, MigrationFilter, SeedPattern};
+    use crate::testutil::habitat_config;
+
+    fn island(connections: Vec<Connection>) -> Island {
+        Island::new(
+            Habitat::new(5, 5, 5),
+            habitat_config(),
+            None,
+            connections,
+            None,
+        )
+    }
+
+    fn connection(amount: usize, mode: MigrationMode) -> Connection {
+        Connection {
+            from_rect: Rectangle::new(0, 0, 5, 5),
+            to_rect: Rectangle::new(0, 0, 5, 5),
+            to_id: 1,
+            transmit_frequency: None,
+            transmit_ticks: Some(Ticks(100)),
+            amount,
+            mode,
+            filter: None,
+            mutation: None,
+        }
+    }
+
+    fn world_state(connection: &Connection) -> WorldState {
+        let mut from_island = island(vec![connection.clone()]);
+        for x in 0..3 {
+            from_island.habitat.set((x, 0), Computer::new(1, 1));
+        }
+        let world_state = WorldState {
+            islands: vec![
+                Arc::new(Mutex::new(from_island)),
+                Arc::new(Mutex::new(island(Vec::new()))),
+            ],
+            observed_island: 0,
+            migrations: MigrationLog::default(),
+            history: HistoryConfig::default(),
+            autosave: AutosaveStatus::default(),
+            events: Arc::new(EventBus::default()),
+            observers: ObserverFactories::default(),
+            followers: Arc::new(Followers::default()),
+        };
+        world_state.number_islands();
+        world_state
+    }
+
+    fn connections(world_state: &WorldState, island_id: usize) -> Vec<Connection> {
+        world_state.islands[island_id]
+            .lock()
+            .unwrap()
+            .connections
+            .clone()
+    }
+
+    fn computers_amounts(world_state: &WorldState) -> Vec<u64> {
+        world_state
+            .islands
+            .iter()
+            .map(|island| island.lock().unwrap().habitat.computers_amount())
+            .collect()
+    }
+
+    #[test]
+    fn test_transfer_move() {
+        let connection = connection(2, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        assert_eq!(world_state.transfer(0, 0, &connection, &mut rng), 2);
+        assert_eq!(computers_amounts(&world_state), vec![1, 2]);
+        let flows = world_state.migrations.flows();
+        assert_eq!(flows.len(), 1);
+        assert_eq!(flows[0].migrants, 2);
+        assert_eq!(flows[0].genotypes[0].count, 2);
+    }
+
+    #[test]
+    fn test_transfer_events() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        let mut events_rx = world_state.events.subscribe();
+        let mut rng = SmallRng::from_seed([0; 32]);
+        assert_eq!(world_state.transfer(0, 0, &connection, &mut rng), 1);
+        let event = events_rx.try_recv().unwrap();
+        assert_eq!(event.island_id, 0);
+        match event.kind {
+            EventKind::Migration {
+                computer_id,
+                to_island_id,
+                migrant_id,
+                ..
+            } => {
+                assert!((1..=3).contains(&computer_id));
+                assert_eq!(to_island_id, 1);
+                assert_eq!(migrant_id, 1);
+            }
+            kind => panic!("unexpected event {:?}", kind),
+        }
+        // and it arrives on the other island
+        let event = events_rx.try_recv().unwrap();
+        assert_eq!(event.island_id, 1);
+        assert!(matches!(
+            event.kind,
+            EventKind::Arrival { computer_id: 1, .. }
+        ));
+    }
+
+    // the island and type of every event
+    struct Recorder(Arc<Mutex<Vec<(usize, EventType)>>>);
+
+    impl IslandObserver for Recorder {
+        fn on_event(&mut self, island: &Island, event: &Event) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((island.id, event.kind.event_type()));
+        }
+    }
+
+    #[test]
+    fn test_observers() {
+        let connection = connection(2, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        let observed = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::new(Mutex::new(Vec::new()));
+        world_state.add_observer({
+            let observed = Arc::clone(&observed);
+            let recorded = Arc::clone(&recorded);
+            Box::new(move |island: &Island| {
+                observed.lock().unwrap().push(island.id);
+                Box::new(Recorder(Arc::clone(&recorded))) as Box<dyn IslandObserver>
+            })
+        });
+        // islands added later get observers of their own too
+        world_state
+            .edit_topology(TopologyEdit::CloneIsland { island_id: 1 })
+            .unwrap();
+        assert_eq!(*observed.lock().unwrap(), vec![0, 1, 2]);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        world_state.transfer(0, 0, &connection, &mut rng);
+        assert_eq!(
+            *recorded.lock().unwrap(),
+            vec![
+                (0, EventType::Migration),
+                (0, EventType::Migration),
+                (1, EventType::Arrival),
+                (1, EventType::Arrival),
+            ]
+        );
+
+        // island 2 is a clone of the empty island 1, and reseeds from island 0
+        recorded.lock().unwrap().clear();
+        world_state
+            .edit_topology(TopologyEdit::AddConnection {
+                island_id: 2,
+                connection: Connection {
+                    to_id: 0,
+                    ..connection.clone()
+                },
+            })
+            .unwrap();
+        world_state.islands[2].lock().unwrap().extinction = Some(Extinction {
+            policy: ExtinctionPolicy::Neighbor,
+            check_frequency: Duration::from_secs(1),
+            pattern: SeedPattern::Scatter {
+                amount: 1,
+                rect: None,
+            },
+            resources: 1,
+        });
+        world_state.check_extinction(2, &mut rng);
+        assert_eq!(
+            *recorded.lock().unwrap(),
+            vec![(2, EventType::Reseed), (2, EventType::Arrival)]
+        );
+    }
+
+    #[test]
+    fn test_transfer_copy() {
+        let connection = connection(2, MigrationMode::Copy);
+        let mut world_state = world_state(&connection);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        assert_eq!(world_state.transfer(0, 0, &connection, &mut rng), 2);
+        assert_eq!(computers_amounts(&world_state), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_transfer_mutated() {
+        let mut connection = connection(1, MigrationMode::Move);
+        connection.mutation = Some(Mutation {
+            overwrite_amount: 1,
+            insert_amount: 4,
+            delete_amount: 0,
+            stack_amount: 0,
+        });
+        let mut world_state = world_state(&connection);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        assert_eq!(world_state.transfer(0, 0, &connection, &mut rng), 1);
+        // the genotype that left the source island
+        let flows = world_state.migrations.flows();
+        assert_eq!(
+            flows[0].genotypes[0].genotype,
+            Computer::new(1, 1).genotype()
+        );
+    }
+
+    #[test]
+    fn test_transfer_filtered() {
+        let mut connection = connection(2, MigrationMode::Move);
+        connection.filter = Some(MigrationFilter {
+            min_resources: Some(2),
+            ..Default::default()
+        });
+        let mut world_state = world_state(&connection);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        assert_eq!(world_state.transfer(0, 0, &connection, &mut rng), 0);
+        assert_eq!(computers_amounts(&world_state), vec![3, 0]);
+    }
+
+    #[test]
+    fn test_add_connection() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        let id = world_state.edit_topology(TopologyEdit::AddConnection {
+            island_id: 1,
+            connection: Connection {
+                to_id: 0,
+                ..connection.clone()
+            },
+        });
+        assert_eq!(id, Ok(0));
+        assert_eq!(connections(&world_state, 1).len(), 1);
+
+        // the rectangle doesn't fit
+        let result = world_state.edit_topology(TopologyEdit::AddConnection {
+            island_id: 1,
+            connection: Connection {
+                to_id: 0,
+                from_rect: Rectangle::new(3, 3, 5, 5),
+                ..connection.clone()
+            },
+        });
+        assert!(result
+            .unwrap_err()
+            .contains("islands[1].connections[1].from_rect"));
+        let result = world_state.edit_topology(TopologyEdit::RemoveConnection {
+            island_id: 1,
+            connection_id: 1,
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_clone_and_remove_island() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        let id = world_state.edit_topology(TopologyEdit::CloneIsland { island_id: 0 });
+        assert_eq!(id, Ok(2));
+        assert_eq!(computers_amounts(&world_state), vec![3, 0, 3]);
+        assert!(connections(&world_state, 2).is_empty());
+        world_state
+            .edit_topology(TopologyEdit::AddConnection {
+                island_id: 2,
+                connection: connection.clone(),
+            })
+            .unwrap();
+
+        let removed = Arc::clone(&world_state.islands[0]);
+        world_state
+            .edit_topology(TopologyEdit::RemoveIsland { island_id: 0 })
+            .unwrap();
+        assert!(removed.lock().unwrap().retired);
+        assert_eq!(computers_amounts(&world_state), vec![0, 3]);
+        // the connection to island 1 now goes to island 0
+        assert_eq!(connections(&world_state, 1)[0].to_id, 0);
+    }
+
+    #[test]
+    fn test_clone_history() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        {
+            let island = &mut *world_state.islands[0].lock().unwrap();
+            island.history.update(Ticks(0), &island.habitat);
+        }
+        world_state.configure_history(None, Some(5));
+        world_state.configure_history(Some(Ticks(100000)), None);
+        world_state
+            .edit_topology(TopologyEdit::CloneIsland { island_id: 0 })
+            .unwrap();
+        let source = world_state.islands[0].lock().unwrap();
+        let clone = world_state.islands[2].lock().unwrap();
+        // the source keeps its samples, the clone starts over
+        assert_eq!(source.history.query(None, None, None).len(), 1);
+        assert!(clone.history.query(None, None, None).is_empty());
+        assert_eq!(clone.history.frequency, Ticks(100000));
+        assert_eq!(clone.history.size, 5);
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        let result = world_state.apply_overrides(&[
+            Override::parse("islands[*].config.max_processors=3").unwrap(),
+            Override::parse("islands[0].connections[0].to_id=99").unwrap(),
+        ]);
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("islands[0].connections[0].to_id"));
+        // nothing was applied
+        assert_eq!(connections(&world_state, 0)[0].to_id, 1);
+
+        world_state
+            .apply_overrides(&[Override::parse("islands[*].config.max_processors=3").unwrap()])
+            .unwrap();
+        assert_eq!(
+            world_state.islands[1]
+                .lock()
+                .unwrap()
+                .config()
+                .max_processors,
+            3
+        );
+    }
+
+    #[test]
+    fn test_remove_neighbor() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        world_state.islands[1].lock().unwrap().extinction = Some(Extinction {
+            policy: ExtinctionPolicy::Neighbor,
+            check_frequency: Duration::from_secs(1),
+            pattern: SeedPattern::Scatter {
+                amount: 1,
+                rect: None,
+            },
+            resources: 1,
+        });
+        // island 1 would have no neighbors left to reseed from
+        let result = world_state.edit_topology(TopologyEdit::RemoveIsland { island_id: 0 });
+        assert!(result.unwrap_err().contains("islands[0].extinction.policy"));
+        assert_eq!(world_state.islands.len(), 2);
+        assert_eq!(connections(&world_state, 0).len(), 1);
+    }
+
+    #[test]
+    fn test_remove_last_connection() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        world_state.islands[1].lock().unwrap().extinction = Some(Extinction {
+            policy: ExtinctionPolicy::Neighbor,
+            check_frequency: Duration::from_secs(1),
+            pattern: SeedPattern::Scatter {
+                amount: 1,
+                rect: None,
+            },
+            resources: 1,
+        });
+        let result = world_state.edit_topology(TopologyEdit::RemoveConnection {
+            island_id: 0,
+            connection_id: 0,
+        });
+        assert!(result.unwrap_err().contains("islands[1].extinction.policy"));
+        assert_eq!(connections(&world_state, 0).len(), 1);
+
+        // with another connection it can go
+        world_state
+            .edit_topology(TopologyEdit::AddConnection {
+                island_id: 1,
+                connection: Connection {
+                    to_id: 0,
+                    ..connection.clone()
+                },
+            })
+            .unwrap();
+        let result = world_state.edit_topology(TopologyEdit::RemoveConnection {
+            island_id: 0,
+            connection_id: 0,
+        });
+        assert_eq!(result, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_topology_tasks() {
+        let connection = connection(1, MigrationMode::Move);
+        let world_state = Arc::new(Mutex::new(world_state(&connection)));
+        let mut tasks = TopologyTasks::default();
+        tasks.update(&world_state.lock().unwrap(), &world_state);
+        assert_eq!(tasks.tasks.len(), 1);
+        let first = Arc::clone(&tasks.tasks[0].stopped);
+
+        // an unrelated edit leaves the task running
+        let mut world = world_state.lock().unwrap();
+        world
+            .edit_topology(TopologyEdit::AddConnection {
+                island_id: 1,
+                connection: Connection {
+                    to_id: 0,
+                    ..connection.clone()
+                },
+            })
+            .unwrap();
+        tasks.update(&world, &world_state);
+        assert_eq!(tasks.tasks.len(), 2);
+        assert!(Arc::ptr_eq(&tasks.tasks[0].stopped, &first));
+        assert!(!first.load(Ordering::Relaxed));
+
+        world
+            .edit_topology(TopologyEdit::CloneIsland { island_id: 1 })
+            .unwrap();
+        world
+            .edit_topology(TopologyEdit::AddConnection {
+                island_id: 2,
+                connection: connection.clone(),
+            })
+            .unwrap();
+        tasks.update(&world, &world_state);
+        let third = Arc::clone(&tasks.tasks[2].stopped);
+
+        // ids shift, so the connection from island 2 is now another task
+        world
+            .edit_topology(TopologyEdit::RemoveIsland { island_id: 0 })
+            .unwrap();
+        tasks.update(&world, &world_state);
+        assert!(first.load(Ordering::Relaxed));
+        assert!(third.load(Ordering::Relaxed));
+        assert_eq!(tasks.tasks.len(), 1);
+        assert_eq!(
+            tasks.tasks[0].kind,
+            TopologyTaskKind::Connection {
+                from_island_id: 1,
+                connection_id: 0,
+                connection: Box::new(Connection {
+                    to_id: 0,
+                    ..connection.clone()
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_control_island() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        world_state
+            .control_island(1, IslandCommand::Step { ticks: 3 })
+            .unwrap();
+        let control = world_state.islands[1].lock().unwrap().control.clone();
+        assert!(control.paused);
+        assert_eq!(control.steps, 3);
+        world_state
+            .control_island(1, IslandCommand::Resume)
+            .unwrap();
+        assert!(!world_state.islands[1].lock().unwrap().control.paused);
+        assert!(world_state
+            .control_island(
+                1,
+                IslandCommand::SpeedLimit {
+                    ticks_per_second: Some(0.0)
+                }
+            )
+            .is_err());
+        assert!(world_state.control_island(2, IslandCommand::Pause).is_err());
+    }
+
+    #[test]
+    fn test_speed_limit_nan() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        let result = world_state.control_island(
+            0,
+            IslandCommand::SpeedLimit {
+                ticks_per_second: Some(f64::NAN),
+            },
+        );
+        assert!(result.is_err());
+        assert_eq!(
+            world_state.islands[0].lock().unwrap().control.speed_limit,
+            None
+        );
+        // not even the limiter itself panics
+        SpeedLimiter::new().tick(Some(f64::NAN));
+    }
+
+    #[test]
+    fn test_speed_limit_tiny() {
+        let connection = connection(1, MigrationMode::Move);
+        let mut world_state = world_state(&connection);
+        for ticks_per_second in [1e-20, f64::INFINITY, -1.0] {
+            let result = world_state.control_island(
+                0,
+                IslandCommand::SpeedLimit {
+                    ticks_per_second: Some(ticks_per_second),
+                },
+            );
+            assert!(result.is_err());
+        }
+        assert!(world_state
+            .control_island(
+                0,
+                IslandCommand::SpeedLimit {
+                    ticks_per_second: Some(MIN_SPEED_LIMIT)
+                }
+            )
+            .is_ok());
+        SpeedLimiter::new().tick(Some(1e-20));
+    }
+
+    #[test]
+    fn test_metrics_sampler() {
+        let mut island = island(Vec::new());
+        let mut sampler = MetricsSampler::new(&island);
+        sampler.start = Instant::now() - Duration::from_secs(2);
+        sampler.waited(Duration::from_millis(500));
+        island.ticks = Ticks(1000);
+        island.metrics.instructions = 4000;
+        sampler.sample(&mut island);
+        // a little more than 2 seconds have passed
+        assert!(island.metrics.ticks_per_second > 490.0);
+        assert!(island.metrics.ticks_per_second <= 500.0);
+        assert!(island.metrics.instructions_per_second <= 2000.0);
+        assert!(island.metrics.mutex_wait <= 0.25);
+        assert!(island.metrics.mutex_wait > 0.24);
+        assert!(!sampler.is_due());
+    }
+
+    #[test]
+    fn test_transfer_same_island() {
+        let mut connection = connection(1, MigrationMode::Copy);
+        connection.to_id = 0;
+        let mut world_state = world_state(&connection);
+        let mut rng = SmallRng::from_seed([0; 32]);
+        assert_eq!(world_state.transfer(0, 0, &connection, &mut rng), 1);
+        assert_eq!(computers_amounts(&world_state), vec![4, 0]);
+    }
+}
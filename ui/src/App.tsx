@@ -7,19 +7,25 @@ import {
   createMemo,
 } from "solid-js";
 
-import { World, Location } from "./world";
+import { World, Location, ViewRequest } from "./world";
 import {
   renderWorld,
   updateWorld,
   getWorldDimensions,
   RenderData,
+  BOX_SIZE,
+  MAX_FULL_LOCATIONS,
 } from "./renderWorld";
 import RenderCanvas from "./RenderCanvas";
 import Sidebar from "./Sidebar";
 import { Viewport } from "pixi-viewport";
 import { FillScheme, fillSchemes } from "./fillScheme";
+import { debounce } from "./debounce";
 
-const socket = new WebSocket("ws://localhost:3000/ws");
+const socket = new WebSocket(`ws://${window.location.host}/ws`);
+
+// about how many blocks the overview has along the longest side
+const OVERVIEW_BLOCKS = 100;
 
 const App: Component = () => {
   const [world, setWorld] = createSignal<World | undefined>();
@@ -42,6 +48,38 @@ const App: Component = () => {
     setWorld(JSON.parse(event.data));
   };
 
+  // what the server sends follows what's in view
+  let viewing = false;
+  const handleViewportChange = debounce((viewport: Viewport) => {
+    const current = world();
+    if (current == null) {
+      return;
+    }
+    const island = current.islands[current.observedIslandId];
+    if (island.width * island.height <= MAX_FULL_LOCATIONS) {
+      if (viewing) {
+        socket.send("full");
+        viewing = false;
+      }
+      return;
+    }
+    const request: ViewRequest = {
+      viewport: {
+        x: Math.max(0, Math.floor(viewport.left / BOX_SIZE)),
+        y: Math.max(0, Math.floor(viewport.top / BOX_SIZE)),
+        w: Math.ceil(viewport.worldScreenWidth / BOX_SIZE) + 1,
+        h: Math.ceil(viewport.worldScreenHeight / BOX_SIZE) + 1,
+      },
+      blockSize: Math.max(
+        1,
+        Math.ceil(Math.max(island.width, island.height) / OVERVIEW_BLOCKS)
+      ),
+      aggregate: "mean",
+    };
+    socket.send(JSON.stringify(request));
+    viewing = true;
+  }, 100);
+
   onMount(() => {
     socket.addEventListener("message", handleWorldUpdate);
   });
@@ -109,6 +147,7 @@ const App: Component = () => {
             data={world}
             getDimensions={getWorldDimensions}
             onClick={handleClick}
+            onViewportChange={handleViewportChange}
           />
         </div>
         <div class="w-2/6">
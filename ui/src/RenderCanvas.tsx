@@ -9,6 +9,8 @@ function RenderCanvas<T, R, C>(props: {
   update: (data: T, renderData: R) => void;
   getDimensions: (data: T) => [number, number];
   onClick: (c: C) => void;
+  // when the viewport is created, dragged or zoomed
+  onViewportChange?: (viewport: Viewport) => void;
 }) {
   let pixiContainer: HTMLDivElement | undefined;
 
@@ -42,7 +44,9 @@ function RenderCanvas<T, R, C>(props: {
     });
 
     viewport.bounce({});
-    viewport.drag();
+    viewport.drag().pinch().wheel();
+    viewport.on("moved", () => props.onViewportChange?.(viewport));
+    viewport.on("zoomed", () => props.onViewportChange?.(viewport));
     return viewport;
   };
 
@@ -78,6 +82,7 @@ function RenderCanvas<T, R, C>(props: {
         renderHeight = height;
         const viewport = createViewport(app, width, height);
         renderData = props.render(viewport, data, props.onClick);
+        props.onViewportChange?.(viewport);
       } else {
         props.update(data, renderData);
       }
@@ -1,12 +1,31 @@
 import * as pixi from "pixi.js";
 
-import { World, Location } from "./world";
+import { World, Location, View, Block } from "./world";
 import { Viewport } from "pixi-viewport";
 
-const BOX_SIZE = 20;
+export const BOX_SIZE = 20;
+// larger islands are shown as an overview, with only the locations in view
+// in full
+export const MAX_FULL_LOCATIONS = 200 * 200;
+
+type OnClick = (options: { location: Location; x: number; y: number }) => void;
 
 export type RenderData = {
+  // the details are drawn over the overview
+  layers: {
+    locations: pixi.Container;
+    blocks: pixi.Container;
+    details: pixi.Container;
+  };
+  onClick: OnClick;
+  // every location, for islands small enough to send them all
   sprites: pixi.Sprite[][];
+  // otherwise an overview of the island, with the locations in view drawn
+  // over it
+  blocks: pixi.Sprite[][];
+  blockSize: number;
+  details: pixi.Sprite[][];
+  view?: View;
 };
 
 const RED = 0xff0000;
@@ -15,26 +34,23 @@ export function renderWorld(
   viewport: Viewport,
   world: World,
   getFill: (location: Location) => number,
-  onClick: (options: { location: Location; x: number; y: number }) => void
+  onClick: OnClick
 ): RenderData {
-  const renderData: RenderData = { sprites: [] };
-  for (let iy = 0; iy < world.locations.length; iy++) {
-    const row = world.locations[iy];
-    const renderRow: pixi.Sprite[] = [];
-    for (let ix = 0; ix < row.length; ix++) {
-      const location = row[ix];
-      const fill = getFill(location);
-      const sprite = new pixi.Sprite(pixi.Texture.WHITE);
-      drawBox(sprite, ix, iy, BOX_SIZE, fill);
-      sprite.interactive = true;
-      sprite.on("pointerdown", () => {
-        onClick({ location, x: ix, y: iy });
-      });
-      renderRow.push(sprite);
-      viewport.addChild(sprite);
-    }
-    renderData.sprites.push(renderRow);
-  }
+  const layers = {
+    locations: new pixi.Container(),
+    blocks: new pixi.Container(),
+    details: new pixi.Container(),
+  };
+  viewport.addChild(layers.locations, layers.blocks, layers.details);
+  const renderData: RenderData = {
+    layers,
+    onClick,
+    sprites: [],
+    blocks: [],
+    blockSize: 0,
+    details: [],
+  };
+  updateWorld(world, renderData, getFill);
   return renderData;
 }
 
@@ -43,15 +59,164 @@ export function updateWorld(
   renderData: RenderData,
   getFill: (location: Location) => number
 ) {
-  for (let iy = 0; iy < world.locations.length; iy++) {
-    const row = world.locations[iy];
+  renderData.view = world.view;
+  if (world.view != null) {
+    renderData.sprites = removeSprites(renderData.sprites);
+    updateOverview(world.view, renderData, getFill);
+    updateDetails(world.view, renderData, getFill);
+  } else {
+    renderData.blocks = removeSprites(renderData.blocks);
+    renderData.details = removeSprites(renderData.details);
+    const island = world.islands[world.observedIslandId];
+    // the frames before the view request is answered are too much to draw
+    if (island.width * island.height <= MAX_FULL_LOCATIONS) {
+      updateLocations(world.locations, renderData, getFill);
+    }
+  }
+}
+
+function updateLocations(
+  locations: Location[][],
+  renderData: RenderData,
+  getFill: (location: Location) => number
+) {
+  if (renderData.sprites.length !== locations.length) {
+    removeSprites(renderData.sprites);
+    renderData.sprites = locations.map((row, iy) =>
+      row.map((_, ix) => {
+        const sprite = addSprite(
+          renderData.layers.locations,
+          ix,
+          iy,
+          BOX_SIZE
+        );
+        sprite.interactive = true;
+        sprite.on("pointerdown", () => {
+          renderData.onClick({ location: locations[iy][ix], x: ix, y: iy });
+        });
+        return sprite;
+      })
+    );
+  }
+  for (let iy = 0; iy < locations.length; iy++) {
+    const row = locations[iy];
+    for (let ix = 0; ix < row.length; ix++) {
+      renderData.sprites[iy][ix].tint = getFill(row[ix]);
+    }
+  }
+}
+
+function updateOverview(
+  view: View,
+  renderData: RenderData,
+  getFill: (location: Location) => number
+) {
+  const { blockSize, blocks } = view.overview;
+  if (
+    renderData.blockSize !== blockSize ||
+    renderData.blocks.length !== blocks.length
+  ) {
+    removeSprites(renderData.blocks);
+    renderData.blockSize = blockSize;
+    renderData.blocks = blocks.map((row, iy) =>
+      row.map((_, ix) =>
+        addSprite(renderData.layers.blocks, ix, iy, blockSize * BOX_SIZE)
+      )
+    );
+  }
+  for (let iy = 0; iy < blocks.length; iy++) {
+    const row = blocks[iy];
     for (let ix = 0; ix < row.length; ix++) {
-      const location = row[ix];
-      const graphics = renderData.sprites[iy][ix];
-      const fill = getFill(location);
-      graphics.tint = fill;
+      renderData.blocks[iy][ix].tint = getFill(blockLocation(row[ix]));
+    }
+  }
+}
+
+// the viewport moves around, so its sprites are kept and moved along
+function updateDetails(
+  view: View,
+  renderData: RenderData,
+  getFill: (location: Location) => number
+) {
+  const { viewport, locations } = view;
+  const width = locations.length > 0 ? locations[0].length : 0;
+  if (
+    renderData.details.length !== locations.length ||
+    (renderData.details.length > 0 && renderData.details[0].length !== width)
+  ) {
+    removeSprites(renderData.details);
+    renderData.details = locations.map((row, iy) =>
+      row.map((_, ix) => {
+        const sprite = addSprite(
+          renderData.layers.details,
+          ix,
+          iy,
+          BOX_SIZE
+        );
+        sprite.interactive = true;
+        sprite.on("pointerdown", () => {
+          const current = renderData.view;
+          if (current == null) {
+            return;
+          }
+          renderData.onClick({
+            location: current.locations[iy][ix],
+            x: current.viewport.x + ix,
+            y: current.viewport.y + iy,
+          });
+        });
+        return sprite;
+      })
+    );
+  }
+  for (let iy = 0; iy < locations.length; iy++) {
+    const row = locations[iy];
+    for (let ix = 0; ix < row.length; ix++) {
+      const sprite = renderData.details[iy][ix];
+      sprite.position.set(
+        (viewport.x + ix) * BOX_SIZE,
+        (viewport.y + iy) * BOX_SIZE
+      );
+      sprite.tint = getFill(row[ix]);
+    }
+  }
+}
+
+// overviews are requested with mean aggregates, so a block looks like a
+// location with a computer on it if most of its locations have one
+function blockLocation(block: Block): Location {
+  if (block.computers < 0.5) {
+    return { freeResources: block.freeResources, computer: null };
+  }
+  return {
+    freeResources: block.freeResources,
+    computer: {
+      memorySize: block.memorySize / block.computers,
+      processors: block.processors / block.computers,
+      boundResources: block.boundResources / block.computers,
+    },
+  };
+}
+
+function addSprite(
+  container: pixi.Container,
+  x: number,
+  y: number,
+  size: number
+): pixi.Sprite {
+  const sprite = new pixi.Sprite(pixi.Texture.WHITE);
+  drawBox(sprite, x, y, size, RED);
+  container.addChild(sprite);
+  return sprite;
+}
+
+function removeSprites(sprites: pixi.Sprite[][]): pixi.Sprite[][] {
+  for (const row of sprites) {
+    for (const sprite of row) {
+      sprite.destroy();
     }
   }
+  return [];
 }
 
 export function getWorldDimensions(world: World): [number, number] {
@@ -2,6 +2,42 @@ export type World = {
   islands: Island[];
   observedIslandId: number;
   locations: Location[][];
+  view?: View;
+};
+
+export type Rectangle = {
+  x: number;
+  y: number;
+  w: number;
+  h: number;
+};
+
+// sent over the websocket as JSON to get a view instead of every location;
+// send "full" to go back
+export type ViewRequest = {
+  viewport: Rectangle;
+  blockSize?: number;
+  aggregate?: "sum" | "mean";
+};
+
+export type View = {
+  viewport: Rectangle;
+  locations: Location[][];
+  overview: Overview;
+};
+
+export type Overview = {
+  blockSize: number;
+  aggregate: "sum" | "mean";
+  blocks: Block[][];
+};
+
+export type Block = {
+  freeResources: number;
+  boundResources: number;
+  computers: number;
+  memorySize: number;
+  processors: number;
 };
 
 export type Island = {